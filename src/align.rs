@@ -0,0 +1,212 @@
+//! Line-by-line alignment of two pretty-printed JSON structures
+//!
+//! Both documents are pretty-printed with object keys in sorted order, and the
+//! lines are paired using the pairing of
+//! [`compare_json_with_options`](crate::compare_json_with_options): object
+//! entries by key and array elements by the element matching. Matched array
+//! elements are placed at their position in the modified document, and removed
+//! elements after the elements that preceded them in the original. Read on its
+//! own, each side of the alignment is valid JSON.
+
+use serde_json::{Map, Value};
+
+use crate::changes::escape_pointer;
+use crate::matching::match_arrays;
+use crate::options::DiffOptions;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum RowKind {
+    Same,
+    Changed,
+    Removed,
+    Added,
+}
+
+/// A line of the original document, of the modified document, or of both
+pub(crate) struct Row {
+    pub left: Option<String>,
+    pub right: Option<String>,
+    pub kind: RowKind,
+    /// JSON Pointer of the value the line belongs to
+    pub path: String,
+}
+
+// Position of a value within its parent, deciding its key prefix and comma
+struct Slot<'a> {
+    key: Option<&'a str>,
+    last: bool,
+}
+
+const ROOT: Slot<'static> = Slot { key: None, last: true };
+
+/// Align the pretty-printed lines of two JSON structures
+pub(crate) fn align_documents(before: &Value, after: &Value, options: &DiffOptions) -> Vec<Row> {
+    let mut rows = Vec::new();
+    align(Some(before), Some(after), &ROOT, &ROOT, "", 0, options, &mut rows);
+    rows
+}
+
+// Emit the aligned rows of a value present on one or both sides
+#[allow(clippy::too_many_arguments)]
+fn align(before: Option<&Value>, after: Option<&Value>, before_slot: &Slot, after_slot: &Slot, path: &str,
+         indent: usize, options: &DiffOptions, rows: &mut Vec<Row>) {
+    match (before, after) {
+        (Some(b_val), Some(a_val)) if b_val == a_val => {
+            let left = pretty_lines(b_val, before_slot, indent);
+            let right = pretty_lines(a_val, after_slot, indent);
+            zip_rows(left, right, RowKind::Same, path, rows);
+        },
+        (Some(Value::Object(before_obj)), Some(Value::Object(after_obj))) => {
+            push_open(before_slot, after_slot, '{', path, indent, rows);
+            align_objects(before_obj, after_obj, path, indent + 2, options, rows);
+            push_close(before_slot, after_slot, '}', path, indent, rows);
+        },
+        (Some(Value::Array(before_arr)), Some(Value::Array(after_arr))) => {
+            push_open(before_slot, after_slot, '[', path, indent, rows);
+            align_arrays(before_arr, after_arr, path, indent + 2, options, rows);
+            push_close(before_slot, after_slot, ']', path, indent, rows);
+        },
+        (Some(b_val), Some(a_val)) => {
+            let left = pretty_lines(b_val, before_slot, indent);
+            let right = pretty_lines(a_val, after_slot, indent);
+            zip_rows(left, right, RowKind::Changed, path, rows);
+        },
+        (Some(b_val), None) => {
+            let left = pretty_lines(b_val, before_slot, indent);
+            zip_rows(left, Vec::new(), RowKind::Removed, path, rows);
+        },
+        (None, Some(a_val)) => {
+            let right = pretty_lines(a_val, after_slot, indent);
+            zip_rows(Vec::new(), right, RowKind::Added, path, rows);
+        },
+        (None, None) => {},
+    }
+}
+
+fn align_objects(before_obj: &Map<String, Value>, after_obj: &Map<String, Value>, path: &str, indent: usize,
+                 options: &DiffOptions, rows: &mut Vec<Row>) {
+    let mut keys: Vec<&String> = before_obj.keys().chain(after_obj.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    let last_before = before_obj.keys().max();
+    let last_after = after_obj.keys().max();
+
+    for key in keys {
+        let before_slot = Slot { key: Some(key), last: Some(key) == last_before };
+        let after_slot = Slot { key: Some(key), last: Some(key) == last_after };
+        let child = format!("{}/{}", path, escape_pointer(key));
+        align(before_obj.get(key), after_obj.get(key), &before_slot, &after_slot, &child, indent, options, rows);
+    }
+}
+
+fn align_arrays(before_arr: &[Value], after_arr: &[Value], path: &str, indent: usize, options: &DiffOptions,
+                rows: &mut Vec<Row>) {
+    let matches = match_arrays(before_arr, after_arr, path, options);
+
+    let mut before_of: Vec<Option<usize>> = vec![None; after_arr.len()];
+    let mut removed_after: Vec<Vec<usize>> = vec![Vec::new(); after_arr.len() + 1];
+    let mut anchor = 0;
+    for (i, match_idx) in matches.iter().enumerate() {
+        match match_idx {
+            Some(j) => {
+                before_of[*j] = Some(i);
+                anchor = anchor.max(j + 1);
+            },
+            None => removed_after[anchor].push(i),
+        }
+    }
+
+    // Display order of the element pairs, one side possibly missing
+    let mut pairs: Vec<(Option<usize>, Option<usize>)> = Vec::new();
+    for (j, removed) in removed_after.iter().enumerate() {
+        pairs.extend(removed.iter().map(|&i| (Some(i), None)));
+        if j < after_arr.len() {
+            pairs.push((before_of[j], Some(j)));
+        }
+    }
+
+    // Commas follow the display order, so that each side stays valid JSON
+    let last_left = pairs.iter().rposition(|(i, _)| i.is_some());
+    let last_right = pairs.iter().rposition(|(_, j)| j.is_some());
+
+    for (n, (i, j)) in pairs.iter().enumerate() {
+        let before_slot = Slot { key: None, last: Some(n) == last_left };
+        let after_slot = Slot { key: None, last: Some(n) == last_right };
+        let child = match (i, j) {
+            (_, Some(j)) => format!("{}/{}", path, j),
+            (Some(i), None) => format!("{}/{}", path, i),
+            (None, None) => continue,
+        };
+        align(i.map(|i| &before_arr[i]), j.map(|j| &after_arr[j]), &before_slot, &after_slot, &child, indent,
+              options, rows);
+    }
+}
+
+fn push_open(before_slot: &Slot, after_slot: &Slot, bracket: char, path: &str, indent: usize, rows: &mut Vec<Row>) {
+    let left = format!("{}{}{}", " ".repeat(indent), key_prefix(before_slot), bracket);
+    let right = format!("{}{}{}", " ".repeat(indent), key_prefix(after_slot), bracket);
+    zip_rows(vec![left], vec![right], RowKind::Same, path, rows);
+}
+
+fn push_close(before_slot: &Slot, after_slot: &Slot, bracket: char, path: &str, indent: usize, rows: &mut Vec<Row>) {
+    let comma = |slot: &Slot| if slot.last { "" } else { "," };
+    let left = format!("{}{}{}", " ".repeat(indent), bracket, comma(before_slot));
+    let right = format!("{}{}{}", " ".repeat(indent), bracket, comma(after_slot));
+    zip_rows(vec![left], vec![right], RowKind::Same, path, rows);
+}
+
+// Pair up the lines of both sides, padding the shorter side with empty cells.
+// Lines of equal values that differ only in their trailing comma are changed.
+fn zip_rows(left: Vec<String>, right: Vec<String>, kind: RowKind, path: &str, rows: &mut Vec<Row>) {
+    let count = left.len().max(right.len());
+    let mut left = left.into_iter();
+    let mut right = right.into_iter();
+    for _ in 0..count {
+        let (left, right) = (left.next(), right.next());
+        let kind = if kind == RowKind::Same && left != right { RowKind::Changed } else { kind };
+        rows.push(Row { left, right, kind, path: path.to_string() });
+    }
+}
+
+// Pretty-printed lines of a value at the given indentation, with its key and
+// trailing comma
+fn pretty_lines(value: &Value, slot: &Slot, indent: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    write_pretty(value, &key_prefix(slot), if slot.last { "" } else { "," }, indent, &mut lines);
+    lines
+}
+
+// Pretty-print a value with sorted object keys
+fn write_pretty(value: &Value, prefix: &str, suffix: &str, indent: usize, lines: &mut Vec<String>) {
+    let pad = " ".repeat(indent);
+    match value {
+        Value::Object(obj) if !obj.is_empty() => {
+            lines.push(format!("{}{}{{", pad, prefix));
+            let mut keys: Vec<&String> = obj.keys().collect();
+            keys.sort();
+            for (n, key) in keys.iter().enumerate() {
+                let comma = if n + 1 == keys.len() { "" } else { "," };
+                let key_prefix = format!("{}: ", Value::String(key.to_string()));
+                write_pretty(&obj[key.as_str()], &key_prefix, comma, indent + 2, lines);
+            }
+            lines.push(format!("{}}}{}", pad, suffix));
+        },
+        Value::Array(items) if !items.is_empty() => {
+            lines.push(format!("{}{}[", pad, prefix));
+            for (n, item) in items.iter().enumerate() {
+                let comma = if n + 1 == items.len() { "" } else { "," };
+                write_pretty(item, "", comma, indent + 2, lines);
+            }
+            lines.push(format!("{}]{}", pad, suffix));
+        },
+        _ => lines.push(format!("{}{}{}{}", pad, prefix, value, suffix)),
+    }
+}
+
+fn key_prefix(slot: &Slot) -> String {
+    match slot.key {
+        Some(key) => format!("{}: ", Value::String(key.to_string())),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,363 @@
+//! Detection of changes between two JSON structures
+//!
+//! The walks mirror [`compare_json_with_options`](crate::compare_json_with_options):
+//! object entries are paired by key and array elements with the same matching,
+//! so a document pair has changes exactly when the comparison result contains
+//! a difference.
+
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+
+use crate::comparator::{custom_comparison, Comparison};
+use crate::core::{format_extended_json, parse_embedded};
+use crate::datetime::{instant_delta, within_tolerance};
+use crate::equivalence::{equal_when_coerced, equal_when_normalized, stands_for_missing};
+use crate::matching::{array_semantics, diff_members, match_arrays};
+use crate::options::{ArraySemantics, DiffOptions};
+
+/// Check whether two JSON structures differ
+///
+/// Returns `false` exactly when [`collect_changes`] would list no changes
+/// other than cosmetic ones; array elements that were only reordered are
+/// moved, and so differ. Stops at the first difference found.
+pub fn has_differences(before: &Value, after: &Value, options: &DiffOptions) -> bool {
+    differs(before, after, "", options)
+}
+
+/// [`has_differences`] for two values found at the JSON Pointer `path`
+pub(crate) fn differs(before: &Value, after: &Value, path: &str, options: &DiffOptions) -> bool {
+    if before == after {
+        return false;
+    }
+    if let Some(comparison) = custom_comparison(before, after, path, options) {
+        return comparison != Comparison::Equal;
+    }
+    if equal_when_coerced(before, after, path, options) {
+        return false;
+    }
+
+    match (before, after) {
+        (Value::Object(before_obj), Value::Object(after_obj)) => {
+            let one_sided = |obj: &Map<String, Value>, other: &Map<String, Value>| {
+                obj.iter().any(|(key, value)| !other.contains_key(key) && !stands_for_missing(value, options))
+            };
+            one_sided(before_obj, after_obj) || one_sided(after_obj, before_obj)
+                || before_obj.iter().any(|(key, b_val)| match after_obj.get(key) {
+                    Some(a_val) => {
+                        b_val != a_val && differs(b_val, a_val, &format!("{}/{}", path, escape_pointer(key)), options)
+                    },
+                    None => false,
+                })
+        },
+        (Value::Array(before_arr), Value::Array(after_arr)) => {
+            if let Some(semantics @ (ArraySemantics::Unordered | ArraySemantics::Multiset)) = array_semantics(path, options) {
+                let (removed, added) = diff_members(before_arr, after_arr, semantics);
+                return !removed.is_empty() || !added.is_empty();
+            }
+            if before_arr.len() != after_arr.len() {
+                return true;
+            }
+            let matches = match_arrays(before_arr, after_arr, path, options);
+            let paired: Vec<usize> = matches.iter().flatten().copied().collect();
+            let moved = paired.windows(2).any(|pair| pair[1] < pair[0]);
+            moved || before_arr.iter().zip(matches).any(|(item, match_idx)| match match_idx {
+                Some(j) => differs(item, &after_arr[j], &format!("{}/{}", path, j), options),
+                None => true,
+            })
+        },
+        (Value::String(before_str), Value::String(after_str)) if before_str != after_str => {
+            if equal_when_normalized(before_str, after_str, path, options) {
+                return false;
+            }
+            if let Some((before_doc, after_doc)) = parse_embedded(before_str, after_str, path, options) {
+                return differs(&before_doc, &after_doc, path, options);
+            }
+            match instant_delta(before_str, after_str, path, options) {
+                Some(delta) => !within_tolerance(delta, options),
+                None => true,
+            }
+        },
+        (before_val, after_val) => before_val != after_val,
+    }
+}
+
+/// Kind of a single change between two JSON structures
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChangeKind {
+    /// Value only present in the modified structure
+    Added,
+    /// Value only present in the original structure
+    Removed,
+    /// Value replaced by a different scalar or by a value of another type
+    Modified,
+    /// Array element paired with an element at another position; `from` is
+    /// its path in the original structure. Changes inside the element are
+    /// reported separately.
+    Moved { from: String },
+    /// Value written differently but equal under the comparison options, such
+    /// as a missing key and `null`; reported with
+    /// [`DiffOptions::report_cosmetic`] only
+    Cosmetic,
+}
+
+/// A single change between two JSON structures
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    /// JSON Pointer to the value in the modified structure, or in the original
+    /// structure for removed values
+    pub path: String,
+    /// Kind of change
+    pub kind: ChangeKind,
+    /// Value in the original structure, if present
+    pub before: Option<Value>,
+    /// Value in the modified structure, if present
+    pub after: Option<Value>,
+}
+
+/// List the individual changes between two JSON structures
+///
+/// Values are paired as in [`compare_json_with_options`](crate::compare_json_with_options).
+/// Unlike the comparison result, reordered array elements are reported as
+/// [`ChangeKind::Moved`]; the elements kept in place are the longest run of
+/// paired elements whose order did not change. Changes within documents
+/// embedded in strings continue the path of the string.
+pub fn collect_changes(before: &Value, after: &Value, options: &DiffOptions) -> Vec<Change> {
+    let mut changes = Vec::new();
+    collect(before, after, "", options, &mut changes);
+    changes
+}
+
+/// Format changes one per line with their full JSON Pointer path
+///
+/// Modified values are printed as `/path: old => new`, added and removed values
+/// as `+ /path` and `- /path`, and moved array elements as
+/// `/path: moved from /old-path`. Cosmetic changes are printed as
+/// `~ /path: old => new`, with `missing` for a missing value. The root value
+/// is shown as `(root)`.
+pub fn format_changes_as_paths(changes: &[Change]) -> String {
+    let mut result = String::new();
+
+    for change in changes {
+        let path = if change.path.is_empty() { "(root)" } else { &change.path };
+        let line = match &change.kind {
+            ChangeKind::Added => format!("+ {}", path),
+            ChangeKind::Removed => format!("- {}", path),
+            ChangeKind::Modified => format!("{}: {} => {}", path, render_value(&change.before), render_value(&change.after)),
+            ChangeKind::Moved { from } => format!("{}: moved from {}", path, from),
+            ChangeKind::Cosmetic => {
+                let show = |value: &Option<Value>| if value.is_some() { render_value(value) } else { "missing".to_string() };
+                format!("~ {}: {} => {}", path, show(&change.before), show(&change.after))
+            },
+        };
+        result.push_str(&line);
+        result.push('\n');
+    }
+
+    result
+}
+
+// Compact single-line rendering of a changed value
+fn render_value(value: &Option<Value>) -> String {
+    match value {
+        Some(Value::Object(obj)) => format_extended_json(obj).unwrap_or_else(|| Value::Object(obj.clone()).to_string()),
+        Some(value) => value.to_string(),
+        None => String::new(),
+    }
+}
+
+/// Escape an object key for use in a JSON Pointer (RFC 6901)
+pub(crate) fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn collect(before: &Value, after: &Value, path: &str, options: &DiffOptions, changes: &mut Vec<Change>) {
+    if before == after {
+        return;
+    }
+    if let Some(comparison) = custom_comparison(before, after, path, options) {
+        if comparison != Comparison::Equal {
+            changes.push(change(path.to_string(), ChangeKind::Modified, Some(before), Some(after)));
+        }
+        return;
+    }
+    if equal_when_coerced(before, after, path, options) {
+        return cosmetic(path.to_string(), Some(before), Some(after), options, changes);
+    }
+
+    match (before, after) {
+        (Value::Object(before_obj), Value::Object(after_obj)) => {
+            let keys: BTreeSet<&String> = before_obj.keys().chain(after_obj.keys()).collect();
+            for key in keys {
+                let child = format!("{}/{}", path, escape_pointer(key));
+                match (before_obj.get(key), after_obj.get(key)) {
+                    (Some(b_val), Some(a_val)) => collect(b_val, a_val, &child, options, changes),
+                    (Some(b_val), None) if stands_for_missing(b_val, options) => {
+                        cosmetic(child, Some(b_val), None, options, changes)
+                    },
+                    (None, Some(a_val)) if stands_for_missing(a_val, options) => {
+                        cosmetic(child, None, Some(a_val), options, changes)
+                    },
+                    (Some(b_val), None) => changes.push(change(child, ChangeKind::Removed, Some(b_val), None)),
+                    (None, Some(a_val)) => changes.push(change(child, ChangeKind::Added, None, Some(a_val))),
+                    (None, None) => {},
+                }
+            }
+        },
+        (Value::Array(before_arr), Value::Array(after_arr)) => {
+            if let Some(semantics @ (ArraySemantics::Unordered | ArraySemantics::Multiset)) = array_semantics(path, options) {
+                let (removed, added) = diff_members(before_arr, after_arr, semantics);
+                for i in removed {
+                    changes.push(change(format!("{}/{}", path, i), ChangeKind::Removed, Some(&before_arr[i]), None));
+                }
+                for j in added {
+                    changes.push(change(format!("{}/{}", path, j), ChangeKind::Added, None, Some(&after_arr[j])));
+                }
+                return;
+            }
+            let matches = match_arrays(before_arr, after_arr, path, options);
+            let in_place = longest_ordered_run(&matches);
+            let mut matched_after = vec![false; after_arr.len()];
+
+            for (i, (item, match_idx)) in before_arr.iter().zip(&matches).enumerate() {
+                match match_idx {
+                    Some(j) => {
+                        matched_after[*j] = true;
+                        let child = format!("{}/{}", path, j);
+                        if !in_place[i] {
+                            let kind = ChangeKind::Moved { from: format!("{}/{}", path, i) };
+                            changes.push(change(child.clone(), kind, Some(item), Some(&after_arr[*j])));
+                        }
+                        collect(item, &after_arr[*j], &child, options, changes);
+                    },
+                    None => changes.push(change(format!("{}/{}", path, i), ChangeKind::Removed, Some(item), None)),
+                }
+            }
+            for (j, item) in after_arr.iter().enumerate() {
+                if !matched_after[j] {
+                    changes.push(change(format!("{}/{}", path, j), ChangeKind::Added, None, Some(item)));
+                }
+            }
+        },
+        (Value::String(before_str), Value::String(after_str)) => {
+            if equal_when_normalized(before_str, after_str, path, options) {
+                return cosmetic(path.to_string(), Some(before), Some(after), options, changes);
+            }
+            if let Some((before_doc, after_doc)) = parse_embedded(before_str, after_str, path, options) {
+                return collect(&before_doc, &after_doc, path, options, changes);
+            }
+            match instant_delta(before_str, after_str, path, options) {
+                Some(delta) if within_tolerance(delta, options) => {},
+                _ => changes.push(change(path.to_string(), ChangeKind::Modified, Some(before), Some(after))),
+            }
+        },
+        _ => changes.push(change(path.to_string(), ChangeKind::Modified, Some(before), Some(after))),
+    }
+}
+
+fn change(path: String, kind: ChangeKind, before: Option<&Value>, after: Option<&Value>) -> Change {
+    Change { path, kind, before: before.cloned(), after: after.cloned() }
+}
+
+// Record a difference in representation only, if such changes are reported
+fn cosmetic(path: String, before: Option<&Value>, after: Option<&Value>, options: &DiffOptions, changes: &mut Vec<Change>) {
+    if options.report_cosmetic {
+        changes.push(change(path, ChangeKind::Cosmetic, before, after));
+    }
+}
+
+// Mark the matched elements that form the longest run with increasing indices
+// on both sides; the other matched elements have moved
+fn longest_ordered_run(matches: &[Option<usize>]) -> Vec<bool> {
+    // Patience sorting: tails[k] is the position in `matches` ending the
+    // smallest-valued increasing run of length k + 1
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous: Vec<Option<usize>> = vec![None; matches.len()];
+
+    for (i, match_idx) in matches.iter().enumerate() {
+        let Some(j) = match_idx else { continue };
+        let len = tails.partition_point(|&t| matches[t].is_some_and(|tj| tj < *j));
+        previous[i] = len.checked_sub(1).map(|k| tails[k]);
+        if len == tails.len() {
+            tails.push(i);
+        } else {
+            tails[len] = i;
+        }
+    }
+
+    let mut in_place = vec![false; matches.len()];
+    let mut current = tails.last().copied();
+    while let Some(i) = current {
+        in_place[i] = true;
+        current = previous[i];
+    }
+    in_place
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern::PathPattern;
+    use serde_json::json;
+
+    // Whether the listing holds anything but cosmetic changes
+    fn lists_differences(before: &Value, after: &Value, options: &DiffOptions) -> bool {
+        collect_changes(before, after, options).iter().any(|change| change.kind != ChangeKind::Cosmetic)
+    }
+
+    #[test]
+    fn moved_elements_are_differences() {
+        let (before, after) = (json!([1, 2]), json!([2, 1]));
+        let changes = collect_changes(&before, &after, &DiffOptions::default());
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path, "/1");
+        assert_eq!(changes[0].kind, ChangeKind::Moved { from: "/0".to_string() });
+        assert!(has_differences(&before, &after, &DiffOptions::default()));
+    }
+
+    #[test]
+    fn paths_list_moves_of_differing_documents() {
+        let (before, after) = (json!({"a": [1, 2], "b": "x"}), json!({"a": [2, 1], "b": "y"}));
+        let changes = collect_changes(&before, &after, &DiffOptions::default());
+        assert_eq!(format_changes_as_paths(&changes), "/a/1: moved from /a/0\n/b: \"x\" => \"y\"\n");
+        assert!(has_differences(&before, &after, &DiffOptions::default()));
+
+        let (before, after) = (json!([1, 2]), json!([2, 1]));
+        let listing = format_changes_as_paths(&collect_changes(&before, &after, &DiffOptions::default()));
+        assert_eq!(listing, "/1: moved from /0\n");
+        assert!(has_differences(&before, &after, &DiffOptions::default()));
+    }
+
+    #[test]
+    fn listing_agrees_with_has_differences() {
+        let unordered = DiffOptions {
+            array_semantics: vec![(PathPattern::any(), ArraySemantics::Unordered)],
+            ..DiffOptions::default()
+        };
+        let cosmetic = DiffOptions { missing_as_null: true, report_cosmetic: true, ..DiffOptions::default() };
+        let cases = [
+            (json!([1, 2]), json!([2, 1]), DiffOptions::default()),
+            (json!([1, 2]), json!([2, 1]), unordered),
+            (json!([{"id": "a"}, {"id": "b"}, 3]), json!([{"id": "a"}, {"id": "b"}, 3]), DiffOptions::default()),
+            (json!({"a": [1, 2, 3]}), json!({"a": [1, 3, 2, 4]}), DiffOptions::default()),
+            (json!({"a": {"b": 1}}), json!({"a": {"b": 2}}), DiffOptions::default()),
+            (json!({"a": null}), json!({}), cosmetic),
+        ];
+        for (before, after, options) in &cases {
+            assert_eq!(has_differences(before, after, options), lists_differences(before, after, options),
+                       "{} vs {}", before, after);
+        }
+    }
+
+    #[test]
+    fn longest_ordered_run_leaves_the_fewest_elements_moved() {
+        let all = [Some(0), Some(1), Some(2)];
+        assert_eq!(longest_ordered_run(&all), vec![true, true, true]);
+        assert_eq!(longest_ordered_run(&[Some(2), Some(0), Some(1)]), vec![false, true, true]);
+        assert_eq!(longest_ordered_run(&[None, Some(3), Some(0), None, Some(1), Some(2)]),
+                   vec![false, false, true, false, true, true]);
+
+        let swapped = longest_ordered_run(&[Some(1), Some(0), Some(3), Some(2)]);
+        assert_eq!(swapped.iter().filter(|&&in_place| in_place).count(), 2);
+        assert_eq!(longest_ordered_run(&[None, None]), vec![false, false]);
+    }
+}
@@ -0,0 +1,144 @@
+//! Custom comparison of values selected by path or by predicate
+//!
+//! Comparators let applications plug in their own notion of equality, such as
+//! case-insensitive emails or normalized URLs. Each comparator is selected by a
+//! [`PathPattern`] or by a predicate on the two values, and the first one that
+//! applies to a pair of values decides whether they are equal, in place of the
+//! default comparison. Identical values are always equal, and array elements
+//! are paired before comparators are consulted.
+
+use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::options::DiffOptions;
+use crate::pattern::PathPattern;
+
+/// Outcome of a custom comparison
+#[derive(Debug, Clone, PartialEq)]
+pub enum Comparison {
+    /// The values are equal
+    Equal,
+    /// The values differ and are shown as `"old" => "new"`
+    Different,
+    /// The values differ and the change is shown as this text
+    Custom(String),
+}
+
+type CompareFn = dyn Fn(&Value, &Value) -> Comparison + Send + Sync;
+type PredicateFn = dyn Fn(&str, &Value, &Value) -> bool + Send + Sync;
+
+#[derive(Clone)]
+enum Selector {
+    Path(PathPattern),
+    Predicate(Arc<PredicateFn>),
+}
+
+/// A comparison callback and the values it applies to
+#[derive(Clone)]
+pub struct Comparator {
+    selector: Selector,
+    compare: Arc<CompareFn>,
+}
+
+impl Comparator {
+    /// Compare the values at paths matching `pattern` with `compare`
+    pub fn at_path<F>(pattern: PathPattern, compare: F) -> Comparator
+    where
+        F: Fn(&Value, &Value) -> Comparison + Send + Sync + 'static,
+    {
+        Comparator { selector: Selector::Path(pattern), compare: Arc::new(compare) }
+    }
+
+    /// Compare the values for which `predicate`, given their JSON Pointer path
+    /// and both values, returns `true` with `compare`
+    pub fn when<P, F>(predicate: P, compare: F) -> Comparator
+    where
+        P: Fn(&str, &Value, &Value) -> bool + Send + Sync + 'static,
+        F: Fn(&Value, &Value) -> Comparison + Send + Sync + 'static,
+    {
+        Comparator { selector: Selector::Predicate(Arc::new(predicate)), compare: Arc::new(compare) }
+    }
+
+    fn applies(&self, path: &str, before: &Value, after: &Value) -> bool {
+        match &self.selector {
+            Selector::Path(pattern) => pattern.matches(path),
+            Selector::Predicate(predicate) => predicate(path, before, after),
+        }
+    }
+}
+
+impl fmt::Debug for Comparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.selector {
+            Selector::Path(pattern) => write!(f, "Comparator(path {})", pattern),
+            Selector::Predicate(_) => write!(f, "Comparator(predicate)"),
+        }
+    }
+}
+
+/// Result of the first comparator in [`DiffOptions::comparators`] that applies
+/// to two values, if any; identical values are equal without consulting the
+/// comparators
+pub(crate) fn custom_comparison(before: &Value, after: &Value, path: &str, options: &DiffOptions) -> Option<Comparison> {
+    if options.comparators.is_empty() || before == after {
+        return None;
+    }
+    let comparator = options.comparators.iter().find(|comparator| comparator.applies(path, before, after))?;
+    Some((comparator.compare)(before, after))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::changes::{collect_changes, has_differences, ChangeKind};
+    use crate::core::compare_json_with_options;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn identical_values_are_not_passed_to_comparators() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let comparator = Comparator::when(
+            move |_, before, _| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                before.is_number()
+            },
+            |_, _| Comparison::Different,
+        );
+        let options = DiffOptions { comparators: vec![comparator], ..DiffOptions::default() };
+        let before = json!({"same": {"a": [1, 2]}, "changed": 1});
+        let after = json!({"same": {"a": [1, 2]}, "changed": 2});
+
+        assert!(has_differences(&before, &after, &options));
+        assert_eq!(calls.swap(0, Ordering::SeqCst), 2);
+        compare_json_with_options(&before, &after, &options);
+        assert_eq!(calls.swap(0, Ordering::SeqCst), 2);
+        assert_eq!(collect_changes(&before, &after, &options).len(), 1);
+        assert_eq!(calls.swap(0, Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn comparators_take_precedence_over_coercion() {
+        let strict = Comparator::at_path(PathPattern::new("/count").unwrap(), |before, after| {
+            if before.is_string() != after.is_string() { Comparison::Different } else { Comparison::Equal }
+        });
+        let options = DiffOptions {
+            comparators: vec![strict],
+            coerce_types: vec![PathPattern::any()],
+            report_cosmetic: true,
+            ..DiffOptions::default()
+        };
+        let before = json!({"count": "42", "other": "1"});
+        let after = json!({"count": 42, "other": 1});
+
+        assert!(has_differences(&before, &after, &options));
+        assert_eq!(compare_json_with_options(&before, &after, &options),
+                   json!({"count": "\"42\" => 42", "other": {"$cosmetic": "\"1\" => 1"}}));
+        let kinds: Vec<(String, ChangeKind)> = collect_changes(&before, &after, &options).into_iter()
+            .map(|change| (change.path, change.kind))
+            .collect();
+        assert_eq!(kinds, vec![("/count".to_string(), ChangeKind::Modified), ("/other".to_string(), ChangeKind::Cosmetic)]);
+    }
+}
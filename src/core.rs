@@ -4,6 +4,23 @@ use serde_json::{Value, Map};
 use std::cmp::{min, max};
 use std::collections::HashSet;
 
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::changes::escape_pointer;
+use crate::comparator::{custom_comparison, Comparison};
+use crate::datetime::{format_delta, instant_delta, within_tolerance};
+use crate::equivalence::{equal_when_coerced, equal_when_normalized, equivalent, stands_for_missing};
+use crate::inline::{format_inline_change, format_line_change};
+use crate::matching::{array_semantics, diff_members, match_arrays};
+use crate::options::{ArraySemantics, DiffOptions, FormatOptions};
+use crate::pattern::matches_any;
+
+/// Collections with at least this many entries are processed in parallel
+/// when the `parallel` feature is enabled
+#[cfg(feature = "parallel")]
+pub(crate) const PARALLEL_MIN_LEN: usize = 32;
+
 /// Compare two JSON structures and return a Value representing the differences
 ///
 /// # Arguments
@@ -17,7 +34,59 @@ use std::collections::HashSet;
 /// * Deleted elements marked with `[-]`
 /// * Changed values shown as `"old" => "new"`
 pub fn compare_json(before: &Value, after: &Value, add_idx: Option<bool>) -> Value {
-    let add_indexes = add_idx.unwrap_or(false);
+    let options = DiffOptions {
+        add_idx: add_idx.unwrap_or(false),
+        ..DiffOptions::default()
+    };
+    compare_json_with_options(before, after, &options)
+}
+
+/// Compare two JSON structures using the given options
+///
+/// Produces the same representation of differences as [`compare_json`], with
+/// these additions depending on the options:
+/// * Documents embedded in strings selected by [`DiffOptions::embedded_json`]
+///   are compared recursively; their differences are wrapped in a
+///   `{"$json": ...}` object, rendered as `JSON(...)`
+/// * Date-times selected by [`DiffOptions::datetime`] are compared as
+///   instants; a change is written as `{"$datetime": "old => new", "$delta": "+1h"}`
+///   and rendered with the time between them
+/// * Values selected by [`DiffOptions::comparators`] are compared by the first
+///   applicable comparator; a change with a custom rendering is written as
+///   `{"$change": "text"}`
+/// * Arrays compared as sets or multisets (see [`DiffOptions::array_semantics`])
+///   hold only their removed and added members
+/// * Values only equal because of the equivalence options are kept as they
+///   are, or with [`DiffOptions::report_cosmetic`] written as
+///   `{"$cosmetic": "missing => null"}` and rendered as a cosmetic change
+///
+/// Objects of the compared documents whose keys are all among those used above
+/// (or `$literal`) are wrapped in a `{"$literal": ...}` object, so that they
+/// are rendered as they are.
+pub fn compare_json_with_options(before: &Value, after: &Value, options: &DiffOptions) -> Value {
+    compare_at(before, after, "", options)
+}
+
+/// Compare two values found at the JSON Pointer `path`
+pub(crate) fn compare_at(before: &Value, after: &Value, path: &str, options: &DiffOptions) -> Value {
+    let add_indexes = options.add_idx;
+    
+    if before == after {
+        return literal(before.clone());
+    }
+    match custom_comparison(before, after, path, options) {
+        Some(Comparison::Equal) => return literal(before.clone()),
+        Some(Comparison::Different) => return replaced(before, after),
+        Some(Comparison::Custom(text)) => {
+            let mut wrapper = Map::new();
+            wrapper.insert("$change".to_string(), Value::String(text));
+            return Value::Object(wrapper);
+        },
+        None => {},
+    }
+    if equal_when_coerced(before, after, path, options) {
+        return equivalent(Some(before), Some(after), options);
+    }
     
     match (before, after) {
         // Compare objects
@@ -34,8 +103,9 @@ pub fn compare_json(before: &Value, after: &Value, add_idx: Option<bool>) -> Val
                     let similarity = string_similarity(&before_id_str, &after_id_str);
                     if similarity > 0.75 {
                         // This is the same object with a changed identifier
+                        let key_path = format!("{}/{}", path, escape_pointer(&before_key));
                         result.insert(before_key.clone(), 
-                            Value::String(format!("{} => {}", before_id_str, after_id_str)));
+                            compare_at(&before_obj[&before_key], &after_obj[&before_key], &key_path, options));
                         
                         // Process the rest of the keys
                         let all_keys: HashSet<String> = before_obj.keys()
@@ -44,29 +114,9 @@ pub fn compare_json(before: &Value, after: &Value, add_idx: Option<bool>) -> Val
                             .cloned()
                             .collect();
                         
-                        for key in all_keys {
-                            let before_val = before_obj.get(&key);
-                            let after_val = after_obj.get(&key);
-                            
-                            match (before_val, after_val) {
-                                (Some(b_val), Some(a_val)) => {
-                                    if b_val == a_val {
-                                        result.insert(key, b_val.clone());
-                                    } else {
-                                        result.insert(key, compare_json(b_val, a_val, Some(add_indexes)));
-                                    }
-                                },
-                                (Some(b_val), None) => {
-                                    result.insert(key, mark_deleted(b_val.clone()));
-                                },
-                                (None, Some(a_val)) => {
-                                    result.insert(key, mark_added(a_val.clone()));
-                                },
-                                _ => unreachable!(),
-                            }
-                        }
+                        result.extend(compare_entries(all_keys, before_obj, after_obj, path, options));
                         
-                        return Value::Object(result);
+                        return escape_object(result);
                     }
                 }
             }
@@ -77,108 +127,56 @@ pub fn compare_json(before: &Value, after: &Value, add_idx: Option<bool>) -> Val
                 .cloned()
                 .collect();
             
-            for key in all_keys {
-                let before_val = before_obj.get(&key);
-                let after_val = after_obj.get(&key);
-                
-                match (before_val, after_val) {
-                    (Some(b_val), Some(a_val)) => {
-                        if b_val == a_val {
-                            result.insert(key, b_val.clone());
-                        } else {
-                            result.insert(key, compare_json(b_val, a_val, Some(add_indexes)));
-                        }
-                    },
-                    (Some(b_val), None) => {
-                        result.insert(key, mark_deleted(b_val.clone()));
-                    },
-                    (None, Some(a_val)) => {
-                        result.insert(key, mark_added(a_val.clone()));
-                    },
-                    _ => unreachable!(),
-                }
-            }
+            result.extend(compare_entries(all_keys, before_obj, after_obj, path, options));
             
-            Value::Object(result)
+            escape_object(result)
         },
         
         // Compare arrays
         (Value::Array(before_arr), Value::Array(after_arr)) => {
+            if let Some(semantics @ (ArraySemantics::Unordered | ArraySemantics::Multiset)) = array_semantics(path, options) {
+                return compare_members(before_arr, after_arr, semantics);
+            }
+            
             let mut result = Vec::new();
             let mut matched_indices = vec![false; after_arr.len()];
-            let similarity_threshold = 0.75;
             let mut object_index = 0;
             
+            let matches = match_arrays(before_arr, after_arr, path, options);
+            let compared_items = compare_matched(before_arr, after_arr, &matches, path, options);
+            
             // Process elements from before_arr
-            for before_item in before_arr {
-                let mut best_match_idx = None;
-                let mut best_similarity = 0.0;
+            for ((before_item, match_idx), compared) in before_arr.iter().zip(matches).zip(compared_items) {
+                let is_object = before_item.is_object();
                 
-                // If this is an object, find match by identifier
-                if let Value::Object(before_obj) = before_item {
-                    let before_id = get_identifier(before_obj);
+                if let (Some(idx), Some(mut compared)) = (match_idx, compared) {
+                    // Found a match
+                    matched_indices[idx] = true;
                     
-                    // Check each element from after_arr
-                    for (i, after_item) in after_arr.iter().enumerate() {
-                        if matched_indices[i] {
-                            continue; // Element already matched
-                        }
-                        
-                        if let Value::Object(after_obj) = after_item {
-                            let after_id = get_identifier(after_obj);
-                            
-                            // If both elements have string identifiers
-                            if let (Some(Value::String(before_str)), Some(Value::String(after_str))) = 
-                                    (before_id.as_ref(), after_id) {
-                                let similarity = string_similarity(before_str, &after_str);
-                                if similarity > similarity_threshold && similarity > best_similarity {
-                                    best_similarity = similarity;
-                                    best_match_idx = Some(i);
-                                }
-                            }
+                    // Add idx field if requested
+                    if add_indexes && is_object {
+                        if let Some(obj) = entries_mut(&mut compared) {
+                            obj.insert("idx".to_string(), Value::Number(serde_json::Number::from(object_index)));
                         }
                     }
                     
-                    if let Some(idx) = best_match_idx {
-                        // Found a match
-                        matched_indices[idx] = true;
-                        let mut compared = compare_json(before_item, &after_arr[idx], Some(add_indexes));
-                        
-                        // Add idx field if requested
-                        if add_indexes {
-                            if let Value::Object(obj) = &mut compared {
-                                obj.insert("idx".to_string(), Value::Number(serde_json::Number::from(object_index)));
-                            }
-                        }
-                        
-                        result.push(compared);
-                        object_index += 1;
-                    } else {
-                        // Element was deleted
-                        let mut deleted = mark_deleted(before_item.clone());
-                        
-                        // Add idx field if requested
-                        if add_indexes {
-                            if let Value::Object(obj) = &mut deleted {
-                                obj.insert("idx".to_string(), Value::Number(serde_json::Number::from(object_index)));
-                            }
-                        }
-                        
-                        result.push(deleted);
-                        object_index += 1;
-                    }
+                    result.push(compared);
                 } else {
-                    // Not an object, look for exact match
-                    let match_idx = after_arr.iter()
-                        .position(|item| item == before_item);
+                    // Element was deleted
+                    let mut deleted = mark_deleted(before_item.clone());
                     
-                    if let Some(idx) = match_idx {
-                        matched_indices[idx] = true;
-                        result.push(before_item.clone());
-                    } else {
-                        // Element was deleted
-                        result.push(mark_deleted(before_item.clone()));
+                    // Add idx field if requested
+                    if add_indexes {
+                        if let Some(obj) = entries_mut(&mut deleted) {
+                            obj.insert("idx".to_string(), Value::Number(serde_json::Number::from(object_index)));
+                        }
                     }
+                    
+                    result.push(deleted);
+                }
+                
+                if is_object {
+                    object_index += 1;
                 }
             }
             
@@ -190,7 +188,7 @@ pub fn compare_json(before: &Value, after: &Value, add_idx: Option<bool>) -> Val
                     
                     // Add idx field if requested and it's an object
                     if add_indexes {
-                        if let Value::Object(obj) = &mut added {
+                        if let Some(obj) = entries_mut(&mut added) {
                             obj.insert("idx".to_string(), Value::Number(serde_json::Number::from(object_index)));
                             object_index += 1;
                         }
@@ -205,6 +203,25 @@ pub fn compare_json(before: &Value, after: &Value, add_idx: Option<bool>) -> Val
         
         // Compare strings
         (Value::String(before_str), Value::String(after_str)) if before_str != after_str => {
+            if equal_when_normalized(before_str, after_str, path, options) {
+                return equivalent(Some(before), Some(after), options);
+            }
+            let mut wrapper = Map::new();
+            if let Some((before_doc, after_doc)) = parse_embedded(before_str, after_str, path, options) {
+                if before_doc == after_doc {
+                    return before.clone();
+                }
+                wrapper.insert("$json".to_string(), compare_at(&before_doc, &after_doc, path, options));
+                return Value::Object(wrapper);
+            }
+            if let Some(delta) = instant_delta(before_str, after_str, path, options) {
+                if within_tolerance(delta, options) {
+                    return before.clone();
+                }
+                wrapper.insert("$datetime".to_string(), Value::String(format!("{} => {}", before_str, after_str)));
+                wrapper.insert("$delta".to_string(), Value::String(format_delta(delta)));
+                return Value::Object(wrapper);
+            }
             Value::String(format!("{} => {}", before_str, after_str))
         },
         
@@ -215,11 +232,92 @@ pub fn compare_json(before: &Value, after: &Value, add_idx: Option<bool>) -> Val
         
         // Identical values
         (before_val, _) => {
-            before_val.clone()
+            literal(before_val.clone())
         },
     }
 }
 
+// Compare arrays holding a set or multiset of members, keeping only the
+// members removed and added
+fn compare_members(before: &[Value], after: &[Value], semantics: ArraySemantics) -> Value {
+    let (removed, added) = diff_members(before, after, semantics);
+    if removed.is_empty() && added.is_empty() {
+        return Value::Array(before.iter().cloned().map(literal).collect());
+    }
+    
+    // Scalars other than strings are marked as strings, like changed scalars
+    let mark = |value: &Value, suffix: &str| match value {
+        Value::Number(_) | Value::Bool(_) | Value::Null => Value::String(format!("{} {}", value, suffix)),
+        _ if suffix == "[-]" => mark_deleted(value.clone()),
+        _ => mark_added(value.clone()),
+    };
+    let members = removed.iter().map(|&i| mark(&before[i], "[-]"))
+        .chain(added.iter().map(|&j| mark(&after[j], "[+]")))
+        .collect();
+    Value::Array(members)
+}
+
+// A value replaced by another, written as `old => new`
+fn replaced(before: &Value, after: &Value) -> Value {
+    match (before, after) {
+        (Value::String(before_str), Value::String(after_str)) => Value::String(format!("{} => {}", before_str, after_str)),
+        _ => Value::String(format!("{} => {}", before, after)),
+    }
+}
+
+// Compare the values stored under each key of a pair of objects
+fn compare_entries(keys: HashSet<String>, before_obj: &Map<String, Value>, after_obj: &Map<String, Value>,
+                   path: &str, options: &DiffOptions) -> Vec<(String, Value)> {
+    let compare_entry = |key: String| {
+        let value = match (before_obj.get(&key), after_obj.get(&key)) {
+            (Some(b_val), Some(a_val)) => {
+                if b_val == a_val {
+                    literal(b_val.clone())
+                } else {
+                    compare_at(b_val, a_val, &format!("{}/{}", path, escape_pointer(&key)), options)
+                }
+            },
+            (Some(b_val), None) if stands_for_missing(b_val, options) => equivalent(Some(b_val), None, options),
+            (None, Some(a_val)) if stands_for_missing(a_val, options) => equivalent(None, Some(a_val), options),
+            (Some(b_val), None) => mark_deleted(b_val.clone()),
+            (None, Some(a_val)) => mark_added(a_val.clone()),
+            _ => unreachable!(),
+        };
+        (key, value)
+    };
+    
+    // Sorted so that the output does not depend on the map implementation
+    // (serde_json's `preserve_order` feature may be enabled by a dependency)
+    let mut keys: Vec<String> = keys.into_iter().collect();
+    keys.sort();
+    
+    #[cfg(feature = "parallel")]
+    {
+        if keys.len() >= PARALLEL_MIN_LEN {
+            return keys.into_par_iter().map(compare_entry).collect();
+        }
+    }
+    
+    keys.into_iter().map(compare_entry).collect()
+}
+
+// Compare each element of `before` with its matched element of `after`
+fn compare_matched(before: &[Value], after: &[Value], matches: &[Option<usize>], path: &str,
+                   options: &DiffOptions) -> Vec<Option<Value>> {
+    let compare_pair = |(item, match_idx): (&Value, &Option<usize>)| {
+        match_idx.map(|idx| compare_at(item, &after[idx], &format!("{}/{}", path, idx), options))
+    };
+    
+    #[cfg(feature = "parallel")]
+    {
+        if before.len() >= PARALLEL_MIN_LEN {
+            return before.par_iter().zip(matches.par_iter()).map(compare_pair).collect();
+        }
+    }
+    
+    before.iter().zip(matches.iter()).map(compare_pair).collect()
+}
+
 /// Format the comparison result into a human-readable string
 ///
 /// # Arguments
@@ -229,59 +327,55 @@ pub fn compare_json(before: &Value, after: &Value, add_idx: Option<bool>) -> Val
 /// # Returns
 /// A formatted string with proper indentation and special markers for changes
 pub fn format_diff_to_string(value: &Value, indent: usize) -> String {
+    format_diff_with_options(value, indent, &FormatOptions::default())
+}
+
+/// Format a comparison result like [`format_diff_to_string`] using the given
+/// options
+///
+/// With [`FormatOptions::inline_diff_min_len`] set, long modified strings show
+/// only the changed words and characters, bracketed as `{-old-}{+new+}` or
+/// highlighted with colors. With [`FormatOptions::multiline_context`] set,
+/// modified multiline strings are shown as a diff of their lines.
+pub fn format_diff_with_options(value: &Value, indent: usize, options: &FormatOptions) -> String {
     match value {
         Value::Object(obj) => {
             if obj.is_empty() {
                 return "{}".to_string();
             }
             
-            let mut result = String::from("{\n");
-            let mut first = true;
-
-            if let Some(idx_val) = obj.get("idx") {
-                result.push_str(&" ".repeat(indent + 2));
-                result.push_str(&format!("\"idx\":", ));
-                
-                let val_str = format_diff_to_string(idx_val, indent + 2);
-                if val_str.contains('\n') {
-                    result.push_str(&format!(" {}", val_str));
-                } else {
-                    result.push_str(&format!(" {}", val_str));
-                }
-                
-                first = false;
+            // Object of the compared documents that looks like the ones below
+            if let (1, Some(Value::Object(inner))) = (obj.len(), obj.get("$literal")) {
+                return format_object(inner, indent, options);
             }
             
-            for (key, val) in obj {
-                if key == "idx" {
-                    continue;
-                }
-                
-                if !first {
-                    result.push_str(",\n");
-                }
-                first = false;
-                
-                // Current level indentation
-                result.push_str(&" ".repeat(indent + 2));
-                
-                // Key in quotes
-                result.push_str(&format!("\"{}\":", key));
-                
-                // Value with increased indentation
-                let val_str = format_diff_to_string(val, indent + 2);
-                
-                // If value is multiline, add space and value
-                if val_str.contains('\n') {
-                    result.push_str(&format!(" {}", val_str));
-                } else {
-                    result.push_str(&format!(" {}", val_str));
-                }
+            // Differences within a JSON document embedded in a string
+            if let (1, Some(document)) = (obj.len(), obj.get("$json")) {
+                return format!("JSON({})", format_diff_with_options(document, indent, options));
             }
             
-            // Closing brace with indentation
-            result.push_str(&format!("\n{}}}", " ".repeat(indent)));
-            result
+            // Differences in representation only
+            if let (1, Some(Value::String(text))) = (obj.len(), obj.get("$cosmetic")) {
+                return format!("{} (cosmetic)", text);
+            }
+            
+            // Changes rendered by a custom comparator
+            if let (1, Some(Value::String(text))) = (obj.len(), obj.get("$change")) {
+                return text.clone();
+            }
+            
+            // Instants compared as date-times, with the time between them
+            if let (2, Some(change @ Value::String(_)), Some(Value::String(delta))) =
+                    (obj.len(), obj.get("$datetime"), obj.get("$delta")) {
+                return format!("{} ({})", format_diff_with_options(change, indent, options), delta);
+            }
+            
+            // Binary formats represent dates, ObjectIds, etc. as Extended JSON
+            if let Some(rendered) = format_extended_json(obj) {
+                return rendered;
+            }
+            
+            format_object(obj, indent, options)
         },
         Value::Array(arr) => {
             if arr.is_empty() {
@@ -301,7 +395,7 @@ pub fn format_diff_to_string(value: &Value, indent: usize) -> String {
                 result.push_str(&" ".repeat(indent + 2));
                 
                 // Add array element
-                let item_str = format_diff_to_string(item, indent + 2);
+                let item_str = format_diff_with_options(item, indent + 2, options);
                 result.push_str(&item_str);
             }
             
@@ -315,8 +409,16 @@ pub fn format_diff_to_string(value: &Value, indent: usize) -> String {
                 let before = &s[0..idx];
                 let after = &s[(idx + 4)..];
                 
-                // Format as "before" => "after" without escaping quotes in JSON
-                format!("\"{}\" => \"{}\"", before, after)
+                // Strings differing only in line endings or a final newline
+                // have the same lines, and are shown whole
+                let multiline = (before.contains('\n') || after.contains('\n')) && before.lines().ne(after.lines());
+                let longest = before.chars().count().max(after.chars().count());
+                match (options.multiline_context, options.inline_diff_min_len) {
+                    (Some(context), _) if multiline => format_line_change(before, after, indent, context),
+                    (_, Some(min_len)) if longest >= min_len => format_inline_change(before, after, options.color),
+                    // Format as "before" => "after" without escaping quotes in JSON
+                    _ => format!("\"{}\" => \"{}\"", before, after),
+                }
             } else if s.ends_with(" [+]") {
                 // Added element
                 let base = &s[0..(s.len() - 4)];
@@ -336,8 +438,128 @@ pub fn format_diff_to_string(value: &Value, indent: usize) -> String {
     }
 }
 
+// Render the entries of an object, `idx` first
+fn format_object(obj: &Map<String, Value>, indent: usize, options: &FormatOptions) -> String {
+    let mut result = String::from("{\n");
+    let mut first = true;
+
+    if let Some(idx_val) = obj.get("idx") {
+        result.push_str(&" ".repeat(indent + 2));
+        result.push_str("\"idx\":");
+        
+        let val_str = format_diff_with_options(idx_val, indent + 2, options);
+        result.push_str(&format!(" {}", val_str));
+        
+        first = false;
+    }
+    
+    for (key, val) in obj {
+        if key == "idx" {
+            continue;
+        }
+        
+        if !first {
+            result.push_str(",\n");
+        }
+        first = false;
+        
+        // Current level indentation
+        result.push_str(&" ".repeat(indent + 2));
+        
+        // Key in quotes
+        result.push_str(&format!("\"{}\":", key));
+        
+        // Value with increased indentation
+        let val_str = format_diff_with_options(val, indent + 2, options);
+        result.push_str(&format!(" {}", val_str));
+    }
+    
+    // Closing brace with indentation
+    result.push_str(&format!("\n{}}}", " ".repeat(indent)));
+    result
+}
+
+/// Parse a pair of strings as the JSON documents they encode, if the path is
+/// selected by [`DiffOptions::embedded_json`] and both strings hold an object
+/// or an array
+pub(crate) fn parse_embedded(before: &str, after: &str, path: &str, options: &DiffOptions) -> Option<(Value, Value)> {
+    if !matches_any(&options.embedded_json, path) {
+        return None;
+    }
+    let parse = |s: &str| match serde_json::from_str::<Value>(s) {
+        Ok(value) if value.is_object() || value.is_array() => Some(value),
+        _ => None,
+    };
+    Some((parse(before)?, parse(after)?))
+}
+
+// Render a single-key Extended JSON object (`{"$oid": ...}`, `{"$date": ...}`,
+// `{"$binary": {...}}`, ...) in a compact form, keeping change markers
+pub(crate) fn format_extended_json(obj: &Map<String, Value>) -> Option<String> {
+    if obj.len() != 1 {
+        return None;
+    }
+    let (key, value) = obj.iter().next()?;
+    
+    match (key.as_str(), value) {
+        ("$oid", Value::String(s)) => Some(format_marked(s, |id| format!("ObjectId(\"{}\")", id))),
+        ("$date", Value::String(s)) => Some(format_marked(s, |date| format!("Date(\"{}\")", date))),
+        ("$date", Value::Object(inner)) => match inner.get("$numberLong") {
+            Some(Value::String(s)) if inner.len() == 1 => Some(format_marked(s, |ms| format!("Date({})", ms))),
+            _ => None,
+        },
+        ("$numberDecimal", Value::String(s)) => Some(format_marked(s, |n| format!("Decimal(\"{}\")", n))),
+        ("$numberDouble", Value::String(s)) => Some(format_marked(s, |n| n.to_string())),
+        ("$binary", Value::Object(inner)) => {
+            match (inner.get("base64"), inner.get("subType")) {
+                (Some(Value::String(data)), Some(Value::String(subtype))) if !is_marked(subtype) => {
+                    Some(format_marked(data, |data| format_binary(data, subtype)))
+                },
+                _ => None,
+            }
+        },
+        ("$timestamp", Value::Object(inner)) => {
+            let t = format_diff_to_string(inner.get("t")?, 0);
+            let i = format_diff_to_string(inner.get("i")?, 0);
+            Some(format!("Timestamp({}, {})", t, i))
+        },
+        _ => None,
+    }
+}
+
+// Compact description of a base64-encoded byte string
+fn format_binary(data: &str, subtype: &str) -> String {
+    let padding = data.chars().rev().take_while(|c| *c == '=').count();
+    let len = (data.len() / 4 * 3).saturating_sub(padding);
+    let preview = if data.len() > 32 {
+        format!("{}...{}", &data[..16], &data[data.len() - 8..])
+    } else {
+        data.to_string()
+    };
+    format!("Binary({} bytes, subtype {}, \"{}\")", len, subtype, preview)
+}
+
+// Whether a string from the comparison result carries a change marker
+fn is_marked(s: &str) -> bool {
+    s.contains(" => ") || s.ends_with(" [+]") || s.ends_with(" [-]")
+}
+
+// Render a string from the comparison result, applying `render` to the
+// original and new values and keeping the change markers
+fn format_marked<F: Fn(&str) -> String>(s: &str, render: F) -> String {
+    if let Some(idx) = s.find(" => ") {
+        format!("{} => {}", render(&s[..idx]), render(&s[idx + 4..]))
+    } else if let Some(base) = s.strip_suffix(" [+]") {
+        format!("{} [+]", render(base))
+    } else if let Some(base) = s.strip_suffix(" [-]") {
+        format!("{} [-]", render(base))
+    } else {
+        render(s)
+    }
+}
+
 // Calculate string similarity (0.0 - completely different, 1.0 - identical)
-fn string_similarity(s1: &str, s2: &str) -> f64 {
+pub(crate) fn string_similarity(s1: &str, s2: &str) -> f64 {
     if s1 == s2 { return 1.0; }
     if s1.is_empty() || s2.is_empty() { return 0.0; }
     
@@ -362,22 +584,31 @@ fn levenshtein_distance(s1: &str, s2: &str) -> usize {
         return max(m, n);
     }
     
+    edit_distance_matrix(&s1_chars, &s2_chars)[m][n]
+}
+
+// Levenshtein distance matrix between two sequences: entry [i][j] is the edit
+// distance between the first i items of `a` and the first j items of `b`
+pub(crate) fn edit_distance_matrix<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Vec<usize>> {
+    let m = a.len();
+    let n = b.len();
+    
     // Create distance matrix
     let mut dp = vec![vec![0; n+1]; m+1];
     
     // Initialize
-    for i in 0..=m {
-        dp[i][0] = i;
+    for (i, row) in dp.iter_mut().enumerate() {
+        row[0] = i;
     }
     
-    for j in 0..=n {
-        dp[0][j] = j;
+    for (j, cell) in dp[0].iter_mut().enumerate() {
+        *cell = j;
     }
     
     // Fill the matrix
     for i in 1..=m {
         for j in 1..=n {
-            let cost = if s1_chars[i-1] == s2_chars[j-1] { 0 } else { 1 };
+            let cost = if a[i-1] == b[j-1] { 0 } else { 1 };
             dp[i][j] = min(
                 min(dp[i-1][j] + 1, dp[i][j-1] + 1),
                 dp[i-1][j-1] + cost
@@ -385,7 +616,60 @@ fn levenshtein_distance(s1: &str, s2: &str) -> usize {
         }
     }
     
-    dp[m][n]
+    dp
+}
+
+// Keys of the objects written for changes rendered specially
+const RESERVED_KEYS: [&str; 6] = ["$json", "$cosmetic", "$change", "$datetime", "$delta", "$literal"];
+
+// Whether an object could be taken for one written for a special change
+fn is_reserved(obj: &Map<String, Value>) -> bool {
+    !obj.is_empty() && obj.keys().all(|key| RESERVED_KEYS.contains(&key.as_str()))
+}
+
+// Wrap an object built from the compared documents in `{"$literal": ...}` if
+// it could be taken for one written for a special change
+fn escape_object(obj: Map<String, Value>) -> Value {
+    if !is_reserved(&obj) {
+        return Value::Object(obj);
+    }
+    let mut wrapper = Map::new();
+    wrapper.insert("$literal".to_string(), Value::Object(obj));
+    Value::Object(wrapper)
+}
+
+// Whether a value holds an object that has to be escaped
+fn needs_escape(value: &Value) -> bool {
+    match value {
+        Value::Object(obj) => is_reserved(obj) || obj.values().any(needs_escape),
+        Value::Array(items) => items.iter().any(needs_escape),
+        _ => false,
+    }
+}
+
+/// Entries of an object of a comparison result, or of the object wrapped in
+/// `$literal`
+pub(crate) fn entries_mut(value: &mut Value) -> Option<&mut Map<String, Value>> {
+    let Value::Object(obj) = value else {
+        return None;
+    };
+    if obj.len() == 1 && obj.get("$literal").is_some_and(Value::is_object) {
+        return obj.get_mut("$literal").and_then(Value::as_object_mut);
+    }
+    Some(obj)
+}
+
+/// Escape the objects of a value copied from the compared documents that could
+/// be taken for the ones written for special changes
+pub(crate) fn literal(value: Value) -> Value {
+    if !needs_escape(&value) {
+        return value;
+    }
+    match value {
+        Value::Object(obj) => escape_object(obj.into_iter().map(|(key, item)| (key, literal(item))).collect()),
+        Value::Array(items) => Value::Array(items.into_iter().map(literal).collect()),
+        other => other,
+    }
 }
 
 // Find key-value pair to use as object identifier
@@ -409,36 +693,64 @@ fn find_identifier_key_value(obj: &Map<String, Value>) -> Option<(String, Value)
 }
 
 // Get object identifier value
-fn get_identifier(obj: &Map<String, Value>) -> Option<Value> {
+pub(crate) fn get_identifier(obj: &Map<String, Value>) -> Option<Value> {
     find_identifier_key_value(obj).map(|(_, value)| value)
 }
 
 // Mark a deleted element
-fn mark_deleted(mut value: Value) -> Value {
+pub(crate) fn mark_deleted(mut value: Value) -> Value {
     if let Value::Object(obj) = &mut value {
         if let Some((key, Value::String(name))) = find_identifier_key_value(obj) {
             let marked_name = format!("{} [-]", name);
             obj.insert(key, Value::String(marked_name));
-            return Value::Object(obj.clone());
+            return literal(Value::Object(obj.clone()));
         }
     } else if let Value::String(s) = &value {
         return Value::String(format!("{} [-]", s));
     }
     
-    value
+    literal(value)
 }
 
 // Mark an added element
-fn mark_added(mut value: Value) -> Value {
+pub(crate) fn mark_added(mut value: Value) -> Value {
     if let Value::Object(obj) = &mut value {
         if let Some((key, Value::String(name))) = find_identifier_key_value(obj) {
             let marked_name = format!("{} [+]", name);
             obj.insert(key, Value::String(marked_name));
-            return Value::Object(obj.clone());
+            return literal(Value::Object(obj.clone()));
         }
     } else if let Value::String(s) = &value {
         return Value::String(format!("{} [+]", s));
     }
     
-    value
+    literal(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(before: &Value, after: &Value) -> String {
+        format_diff_to_string(&compare_json_with_options(before, after, &DiffOptions::default()), 0)
+    }
+
+    #[test]
+    fn objects_with_reserved_keys_are_rendered_as_they_are() {
+        let before = json!({"a": {"$change": "x"}, "b": {"$json": {"k": 1}}, "c": {"$change": "x"}});
+        let after = json!({"a": {"$change": "x"}, "b": {"$json": {"k": 1}}, "c": {"$change": "y"}});
+
+        let rendered = render(&before, &after);
+        assert!(rendered.contains("\"a\": {\n    \"$change\": \"x\"\n  }"), "{}", rendered);
+        assert!(rendered.contains("\"$json\": {"), "{}", rendered);
+        assert!(rendered.contains("\"$change\": \"x\" => \"y\""), "{}", rendered);
+    }
+
+    #[test]
+    fn literal_leaves_other_values_alone() {
+        let value = json!({"a": [1, {"b": "$change"}], "$json": 1, "c": null});
+        assert_eq!(literal(value.clone()), value);
+        assert_eq!(literal(json!([{"$delta": 1}])), json!([{"$literal": {"$delta": 1}}]));
+    }
 }
@@ -0,0 +1,138 @@
+//! Comparison of date-time strings by the instant they denote
+//!
+//! Strings are parsed as RFC 3339 date-times, with the ISO 8601 variations
+//! commonly found in JSON: a lowercase `t` or a space as separator, offsets
+//! without a colon or without minutes, and dates without a time (taken as
+//! midnight). Date-times without an offset are taken as UTC.
+
+use crate::options::DiffOptions;
+use crate::pattern::matches_any;
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+
+/// Time from the instant of `before` to the instant of `after` in nanoseconds,
+/// if the path is selected by [`DiffOptions::datetime`] and both strings are
+/// date-times
+pub(crate) fn instant_delta(before: &str, after: &str, path: &str, options: &DiffOptions) -> Option<i128> {
+    if !matches_any(&options.datetime, path) {
+        return None;
+    }
+    Some(parse_instant(after)? - parse_instant(before)?)
+}
+
+/// Whether two instants this far apart are treated as equal
+pub(crate) fn within_tolerance(delta: i128, options: &DiffOptions) -> bool {
+    delta.unsigned_abs() <= options.datetime_tolerance.as_nanos()
+}
+
+/// Signed duration such as `+1d 2h 30m` or `-1.5s`
+pub(crate) fn format_delta(delta: i128) -> String {
+    let nanos = delta.unsigned_abs();
+    let seconds = nanos / NANOS_PER_SECOND as u128;
+    let fraction = nanos % NANOS_PER_SECOND as u128;
+
+    let mut parts = Vec::new();
+    for (count, unit) in [(seconds / 86_400, "d"), (seconds / 3_600 % 24, "h"), (seconds / 60 % 60, "m")] {
+        if count > 0 {
+            parts.push(format!("{}{}", count, unit));
+        }
+    }
+    let rest = seconds % 60;
+    if rest > 0 || fraction > 0 || parts.is_empty() {
+        let fraction = format!("{:09}", fraction);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            parts.push(format!("{}s", rest));
+        } else {
+            parts.push(format!("{}.{}s", rest, fraction));
+        }
+    }
+
+    format!("{}{}", if delta < 0 { '-' } else { '+' }, parts.join(" "))
+}
+
+// Nanoseconds since the Unix epoch
+fn parse_instant(s: &str) -> Option<i128> {
+    let bytes = s.as_bytes();
+    let year = number(bytes, 0, 4)?;
+    let month = number(bytes, 5, 2)?;
+    let day = number(bytes, 8, 2)?;
+    if bytes.get(4) != Some(&b'-') || bytes.get(7) != Some(&b'-') || !(1..=12).contains(&month)
+        || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    if bytes.len() == 10 {
+        return Some(days as i128 * 86_400 * NANOS_PER_SECOND);
+    }
+
+    if !matches!(bytes.get(10), Some(b'T' | b't' | b' ')) || bytes.get(13) != Some(&b':') || bytes.get(16) != Some(&b':') {
+        return None;
+    }
+    let hour = number(bytes, 11, 2)?;
+    let minute = number(bytes, 14, 2)?;
+    let second = number(bytes, 17, 2)?;
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    // Fractional seconds, keeping nanosecond precision
+    let mut pos = 19;
+    let mut nanos = 0;
+    if bytes.get(pos) == Some(&b'.') {
+        let digits = bytes[pos + 1..].iter().take_while(|b| b.is_ascii_digit()).count();
+        if digits == 0 {
+            return None;
+        }
+        for k in 0..9 {
+            nanos = nanos * 10 + if k < digits { (bytes[pos + 1 + k] - b'0') as i128 } else { 0 };
+        }
+        pos += 1 + digits;
+    }
+
+    let offset_minutes = match &bytes[pos..] {
+        [] | [b'Z' | b'z'] => 0,
+        [sign @ (b'+' | b'-'), rest @ ..] => {
+            let (hours, minutes) = match rest {
+                [_, _] => (number(rest, 0, 2)?, 0),
+                [_, _, b':', _, _] => (number(rest, 0, 2)?, number(rest, 3, 2)?),
+                [_, _, _, _] => (number(rest, 0, 2)?, number(rest, 2, 2)?),
+                _ => return None,
+            };
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let offset = hours * 60 + minutes;
+            if *sign == b'-' { -offset } else { offset }
+        },
+        _ => return None,
+    };
+
+    let seconds = days * 86_400 + hour * 3_600 + minute * 60 + second - offset_minutes * 60;
+    Some(seconds as i128 * NANOS_PER_SECOND + nanos)
+}
+
+// Decimal number in `len` ASCII digits at `start`
+fn number(bytes: &[u8], start: usize, len: usize) -> Option<i64> {
+    let digits = bytes.get(start..start + len)?;
+    digits.iter().try_fold(0, |n, b| b.is_ascii_digit().then(|| n * 10 + (b - b'0') as i64))
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
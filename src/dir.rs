@@ -0,0 +1,351 @@
+//! Recursive comparison of two directory trees
+//!
+//! Files are paired by their path relative to the compared directories. Each
+//! pair is parsed in the format detected from its extension or contents and
+//! compared with [`compare_json_with_options`]; the records of `.ndjson` and
+//! `.jsonl` files are compared as the elements of an array. Files present on
+//! one side only are reported without being read.
+
+use glob::{MatchOptions, Pattern};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::changes::has_differences;
+use crate::core::{compare_json_with_options, format_diff_to_string};
+use crate::input::{parse_document_bytes, InputFormat};
+use crate::ndjson::parse_ndjson;
+use crate::options::DiffOptions;
+
+/// Selection of the files compared in a directory tree
+///
+/// Patterns containing a `/` are matched against the path relative to the
+/// compared directory, other patterns against the file name only, so `*.json`
+/// selects JSON files at any depth. Excluded directories are not descended
+/// into. Without include patterns, all files with a recognized extension
+/// (`.json`, `.yaml`, `.toml`, `.ndjson`, ...) are compared.
+#[derive(Debug, Clone, Default)]
+pub struct DirFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl DirFilter {
+    /// Create a filter from include and exclude glob patterns
+    ///
+    /// # Returns
+    /// The filter, or an error naming the first invalid pattern
+    pub fn new<S: AsRef<str>>(include: &[S], exclude: &[S]) -> Result<DirFilter, String> {
+        let compile = |patterns: &[S]| {
+            patterns.iter()
+                .map(|p| Pattern::new(p.as_ref()).map_err(|e| format!("Invalid pattern '{}': {}", p.as_ref(), e)))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(DirFilter { include: compile(include)?, exclude: compile(exclude)? })
+    }
+
+    /// Whether a file, given by its `/`-separated relative path, is compared
+    pub fn is_included(&self, path: &str) -> bool {
+        let selected = if self.include.is_empty() {
+            InputFormat::from_path(path).is_some() || is_ndjson(path)
+        } else {
+            self.include.iter().any(|pattern| pattern_matches(pattern, path))
+        };
+        selected && !self.is_excluded(path)
+    }
+
+    /// Whether a file or directory, given by its `/`-separated relative path,
+    /// is excluded
+    pub fn is_excluded(&self, path: &str) -> bool {
+        self.exclude.iter().any(|pattern| pattern_matches(pattern, path))
+    }
+}
+
+/// A difference between the files of two directory trees
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileChange {
+    /// File only present in the modified directory
+    Added { path: String },
+    /// File only present in the original directory
+    Removed { path: String },
+    /// File present in both directories with different contents; `diff` is the
+    /// result of comparing the two documents
+    Modified { path: String, diff: Value },
+    /// File present in both directories with equivalent contents
+    Unchanged { path: String },
+    /// File present in both directories that could not be read or parsed
+    Failed { path: String, error: String },
+}
+
+impl FileChange {
+    /// Path of the file relative to the compared directories
+    pub fn path(&self) -> &str {
+        match self {
+            FileChange::Added { path }
+            | FileChange::Removed { path }
+            | FileChange::Modified { path, .. }
+            | FileChange::Unchanged { path }
+            | FileChange::Failed { path, .. } => path,
+        }
+    }
+}
+
+/// Compare the files of two directory trees
+///
+/// # Arguments
+/// * `before` - Original directory
+/// * `after` - Modified directory
+/// * `filter` - Files to compare
+/// * `options` - Options used to compare each pair of files
+///
+/// # Returns
+/// One entry per selected file, ordered by relative path, or an error if a
+/// directory cannot be listed. Files that cannot be read or parsed are
+/// reported as [`FileChange::Failed`] without stopping the comparison.
+pub fn compare_dirs<B, A>(before: B, after: A, filter: &DirFilter, options: &DiffOptions) -> io::Result<Vec<FileChange>>
+where
+    B: AsRef<Path>,
+    A: AsRef<Path>,
+{
+    let (before, after) = (before.as_ref(), after.as_ref());
+
+    let mut before_files = BTreeMap::new();
+    list_files(before, "", filter, &mut Vec::new(), &mut before_files)?;
+    let mut after_files = BTreeMap::new();
+    list_files(after, "", filter, &mut Vec::new(), &mut after_files)?;
+
+    let mut paths: Vec<&String> = before_files.keys().chain(after_files.keys()).collect();
+    paths.sort();
+    paths.dedup();
+    let changes = paths.into_iter()
+        .map(|path| {
+            let path = path.clone();
+            match (before_files.get(&path), after_files.get(&path)) {
+                (Some(Some(error)), _) | (_, Some(Some(error))) => FileChange::Failed { path, error: error.clone() },
+                (Some(_), None) => FileChange::Removed { path },
+                (None, Some(_)) => FileChange::Added { path },
+                _ => match (read_document(&before.join(&path)), read_document(&after.join(&path))) {
+                    (Ok(before_value), Ok(after_value)) => {
+                        if has_differences(&before_value, &after_value, options) {
+                            let diff = compare_json_with_options(&before_value, &after_value, options);
+                            FileChange::Modified { path, diff }
+                        } else {
+                            FileChange::Unchanged { path }
+                        }
+                    },
+                    (Err(error), _) | (_, Err(error)) => FileChange::Failed { path, error },
+                },
+            }
+        })
+        .collect();
+
+    Ok(changes)
+}
+
+/// Format a directory comparison as a per-file summary followed by the diff of
+/// each modified file
+///
+/// Summary lines are prefixed with `+` for added, `-` for removed, `~` for
+/// modified and `!` for unreadable files; unchanged files are only counted.
+pub fn format_dir_diff(changes: &[FileChange]) -> String {
+    let mut result = String::new();
+    let mut unchanged = 0;
+
+    for change in changes {
+        match change {
+            FileChange::Added { path } => result.push_str(&format!("+ {}\n", path)),
+            FileChange::Removed { path } => result.push_str(&format!("- {}\n", path)),
+            FileChange::Modified { path, .. } => result.push_str(&format!("~ {}\n", path)),
+            FileChange::Failed { path, error } => result.push_str(&format!("! {}: {}\n", path, error)),
+            FileChange::Unchanged { .. } => unchanged += 1,
+        }
+    }
+
+    let count = |f: fn(&FileChange) -> bool| changes.iter().filter(|c| f(c)).count();
+    result.push_str(&format!(
+        "{} {} compared: {} modified, {} added, {} removed, {} unchanged",
+        changes.len(),
+        if changes.len() == 1 { "file" } else { "files" },
+        count(|c| matches!(c, FileChange::Modified { .. })),
+        count(|c| matches!(c, FileChange::Added { .. })),
+        count(|c| matches!(c, FileChange::Removed { .. })),
+        unchanged,
+    ));
+    let failed = count(|c| matches!(c, FileChange::Failed { .. }));
+    if failed > 0 {
+        result.push_str(&format!(", {} failed", failed));
+    }
+    result.push('\n');
+
+    for change in changes {
+        if let FileChange::Modified { path, diff } = change {
+            result.push_str(&format!("\n~ {}:\n", path));
+            result.push_str(&format!("  {}\n", format_diff_to_string(diff, 2)));
+        }
+    }
+
+    result
+}
+
+// Collect the relative paths of the selected files below `dir`; `ancestors`
+// holds the canonical paths of the directories being walked, so that a
+// symbolic link to one of them is not followed into a cycle. Entries whose
+// type cannot be determined, such as dangling symbolic links, are listed with
+// the error.
+fn list_files(dir: &Path, prefix: &str, filter: &DirFilter, ancestors: &mut Vec<PathBuf>,
+              files: &mut BTreeMap<String, Option<String>>) -> io::Result<()> {
+    let canonical = fs::canonicalize(dir)?;
+    if ancestors.contains(&canonical) {
+        return Ok(());
+    }
+    let entries = fs::read_dir(dir)
+        .map_err(|e| io::Error::new(e.kind(), format!("Failed to read directory {}: {}", dir.display(), e)))?;
+    ancestors.push(canonical);
+
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let path = format!("{}{}", prefix, name);
+
+        // Follows symbolic links, like reading the file would
+        match fs::metadata(entry.path()) {
+            Ok(metadata) if metadata.is_dir() => {
+                if !filter.is_excluded(&path) {
+                    list_files(&entry.path(), &format!("{}/", path), filter, ancestors, files)?;
+                }
+            },
+            Ok(_) if filter.is_included(&path) => {
+                files.insert(path, None);
+            },
+            Ok(_) => {},
+            Err(e) if filter.is_included(&path) => {
+                files.insert(path, Some(e.to_string()));
+            },
+            Err(_) => {},
+        }
+    }
+
+    ancestors.pop();
+    Ok(())
+}
+
+// Read and parse a file in the format detected from its extension or contents;
+// NDJSON files are read as the array of their records
+fn read_document(path: &Path) -> Result<Value, String> {
+    let data = fs::read(path).map_err(|e| e.to_string())?;
+    if is_ndjson(&path.to_string_lossy()) {
+        let text = String::from_utf8(data).map_err(|e| format!("invalid NDJSON: {}", e))?;
+        let records = parse_ndjson(&text).map_err(|e| format!("invalid NDJSON: {}", e))?;
+        return Ok(Value::Array(records.into_iter().map(|record| record.value).collect()));
+    }
+    let format = InputFormat::from_path(path)
+        .or_else(|| InputFormat::detect(&data))
+        .unwrap_or(InputFormat::Json);
+    parse_document_bytes(&data, format).map_err(|e| format!("invalid {}: {}", format, e))
+}
+
+// Whether a path names an NDJSON file, by its extension
+fn is_ndjson(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    path.ends_with(".ndjson") || path.ends_with(".jsonl")
+}
+
+// Match a pattern against the relative path, or against the file name for
+// patterns without a directory separator
+fn pattern_matches(pattern: &Pattern, path: &str) -> bool {
+    let options = MatchOptions { require_literal_separator: true, ..MatchOptions::new() };
+    if pattern.as_str().contains('/') {
+        pattern.matches_with(path, options)
+    } else {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        pattern.matches_with(name, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Fresh directory with the given files below the system temporary directory
+    fn tree(files: &[(&str, &str)]) -> PathBuf {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!("json-diff-view-dir-{}-{}", std::process::id(),
+                                                    COUNT.fetch_add(1, Ordering::SeqCst)));
+        let _ = fs::remove_dir_all(&dir);
+        for (path, contents) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn compare(before: &Path, after: &Path) -> Vec<FileChange> {
+        compare_dirs(before, after, &DirFilter::default(), &DiffOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn files_are_paired_by_relative_path() {
+        let before = tree(&[("a.json", "{\"x\": 1}"), ("sub/b.yaml", "x: 1"), ("gone.json", "1"), ("notes.txt", "")]);
+        let after = tree(&[("a.json", "{\"x\": 2}"), ("sub/b.yaml", "x: 1"), ("new.json", "1")]);
+
+        let changes = compare(&before, &after);
+        let summary: Vec<(&str, &str)> = changes.iter()
+            .map(|change| (change.path(), match change {
+                FileChange::Added { .. } => "added",
+                FileChange::Removed { .. } => "removed",
+                FileChange::Modified { .. } => "modified",
+                FileChange::Unchanged { .. } => "unchanged",
+                FileChange::Failed { .. } => "failed",
+            }))
+            .collect();
+        assert_eq!(summary, vec![("a.json", "modified"), ("gone.json", "removed"), ("new.json", "added"),
+                                 ("sub/b.yaml", if cfg!(feature = "yaml") { "unchanged" } else { "failed" })]);
+
+        fs::remove_dir_all(before).unwrap();
+        fs::remove_dir_all(after).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dangling_symlinks_fail_without_stopping_the_comparison() {
+        let before = tree(&[("a.json", "1"), ("broken.json", "1")]);
+        let after = tree(&[("a.json", "2")]);
+        std::os::unix::fs::symlink("/nonexistent/target.json", after.join("broken.json")).unwrap();
+        std::os::unix::fs::symlink(&after, after.join("loop")).unwrap();
+
+        let changes = compare(&before, &after);
+        assert_eq!(changes.len(), 2);
+        assert!(matches!(&changes[0], FileChange::Modified { path, .. } if path == "a.json"));
+        assert!(matches!(&changes[1], FileChange::Failed { path, .. } if path == "broken.json"));
+
+        fs::remove_dir_all(before).unwrap();
+        fs::remove_dir_all(after).unwrap();
+    }
+
+    #[test]
+    fn ndjson_records_are_compared_as_array_elements() {
+        let before = tree(&[("log.ndjson", "{\"id\": 1}\n{\"id\": 2}\n"), ("same.jsonl", "1\n2\n")]);
+        let after = tree(&[("log.ndjson", "{\"id\": 1}\n{\"id\": 3}\n"), ("same.jsonl", "1\n\n2\n")]);
+
+        let changes = compare(&before, &after);
+        assert_eq!(changes.len(), 2);
+        assert!(matches!(&changes[0], FileChange::Modified { path, .. } if path == "log.ndjson"));
+        assert!(matches!(&changes[1], FileChange::Unchanged { path } if path == "same.jsonl"));
+
+        fs::remove_dir_all(before).unwrap();
+        fs::remove_dir_all(after).unwrap();
+    }
+
+    #[test]
+    fn summary_counts_are_pluralized() {
+        let single = format_dir_diff(&[FileChange::Unchanged { path: "a.json".to_string() }]);
+        assert!(single.starts_with("1 file compared:"), "{}", single);
+        let none = format_dir_diff(&[]);
+        assert!(none.starts_with("0 files compared:"), "{}", none);
+    }
+}
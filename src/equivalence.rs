@@ -0,0 +1,162 @@
+//! Values treated as equal under the comparison options even though they are
+//! written differently: a missing object key and `null` or an empty container,
+//! strings that are equal once normalized, and scalars of different types
+//! holding the same number or boolean.
+//!
+//! Such pairs are either ignored or, with [`DiffOptions::report_cosmetic`],
+//! reported as cosmetic changes that do not make the documents differ.
+
+use serde_json::{Map, Number, Value};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::core::literal;
+use crate::options::{DiffOptions, Normalization};
+use crate::pattern::matches_any;
+
+/// Whether a value has the same meaning as a missing object key:
+/// `null` with [`DiffOptions::missing_as_null`], and an empty array or object
+/// with [`DiffOptions::missing_as_empty`]
+pub(crate) fn stands_for_missing(value: &Value, options: &DiffOptions) -> bool {
+    match value {
+        Value::Null => options.missing_as_null,
+        Value::Array(items) => options.missing_as_empty && items.is_empty(),
+        Value::Object(obj) => options.missing_as_empty && obj.is_empty(),
+        _ => false,
+    }
+}
+
+/// Whether two different strings are equal after the normalizations selected
+/// for their path by [`DiffOptions::normalizations`]
+pub(crate) fn equal_when_normalized(before: &str, after: &str, path: &str, options: &DiffOptions) -> bool {
+    let selected: Vec<Normalization> = options.normalizations.iter()
+        .filter(|(pattern, _)| pattern.matches(path))
+        .map(|(_, normalization)| *normalization)
+        .collect();
+    !selected.is_empty() && normalize(before, &selected) == normalize(after, &selected)
+}
+
+// Apply the normalizations in a fixed order, whatever order they were given in:
+// line endings and Unicode forms first, so that whitespace and case are
+// compared on the composed text
+fn normalize(s: &str, selected: &[Normalization]) -> String {
+    let mut s = s.to_string();
+    if selected.contains(&Normalization::LineEndings) {
+        s = s.replace("\r\n", "\n").replace('\r', "\n");
+    }
+    if selected.contains(&Normalization::Nfkc) {
+        s = s.nfkc().collect();
+    } else if selected.contains(&Normalization::Nfc) {
+        s = s.nfc().collect();
+    }
+    if selected.contains(&Normalization::Trim) {
+        s = s.trim().to_string();
+    }
+    if selected.contains(&Normalization::Whitespace) {
+        let mut collapsed = String::with_capacity(s.len());
+        for c in s.chars() {
+            if !c.is_whitespace() {
+                collapsed.push(c);
+            } else if !collapsed.ends_with(' ') {
+                collapsed.push(' ');
+            }
+        }
+        s = collapsed;
+    }
+    if selected.contains(&Normalization::Case) {
+        s = s.to_lowercase();
+    }
+    s
+}
+
+/// Whether two different scalars hold the same number or boolean, at paths
+/// selected by [`DiffOptions::coerce_types`]
+pub(crate) fn equal_when_coerced(before: &Value, after: &Value, path: &str, options: &DiffOptions) -> bool {
+    if !matches_any(&options.coerce_types, path) {
+        return false;
+    }
+    match (before, after) {
+        (Value::Number(a), Value::Number(b)) => same_number(a, b),
+        (Value::String(s), Value::Number(n)) | (Value::Number(n), Value::String(s)) => {
+            s.trim().parse::<Number>().is_ok_and(|parsed| same_number(&parsed, n))
+        },
+        (Value::String(s), Value::Bool(b)) | (Value::Bool(b), Value::String(s)) => {
+            s.trim().eq_ignore_ascii_case(if *b { "true" } else { "false" })
+        },
+        _ => false,
+    }
+}
+
+// Integers are compared exactly, other numbers as floating point
+fn same_number(a: &Number, b: &Number) -> bool {
+    if let (Some(x), Some(y)) = (a.as_i64(), b.as_i64()) {
+        return x == y;
+    }
+    if let (Some(x), Some(y)) = (a.as_u64(), b.as_u64()) {
+        return x == y;
+    }
+    a.as_f64() == b.as_f64()
+}
+
+/// Comparison result of two equivalent values, either of them possibly
+/// missing: the value itself, or with [`DiffOptions::report_cosmetic`] a
+/// `{"$cosmetic": "old => new"}` object with both values as JSON
+pub(crate) fn equivalent(before: Option<&Value>, after: Option<&Value>, options: &DiffOptions) -> Value {
+    if !options.report_cosmetic {
+        return before.or(after).cloned().map_or(Value::Null, literal);
+    }
+    let show = |value: Option<&Value>| value.map_or("missing".to_string(), Value::to_string);
+    let mut wrapper = Map::new();
+    wrapper.insert("$cosmetic".to_string(), Value::String(format!("{} => {}", show(before), show(after))));
+    Value::Object(wrapper)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::changes::{collect_changes, has_differences, ChangeKind};
+    use crate::core::compare_json_with_options;
+    use serde_json::json;
+
+    fn missing(report_cosmetic: bool) -> DiffOptions {
+        DiffOptions { missing_as_null: true, missing_as_empty: true, report_cosmetic, ..DiffOptions::default() }
+    }
+
+    #[test]
+    fn missing_keys_equal_null_and_empty_containers() {
+        let before = json!({"a": null, "b": [], "c": 1, "d": {"e": {}}});
+        let after = json!({"c": 1, "d": {"f": null}});
+
+        assert!(has_differences(&before, &after, &DiffOptions::default()));
+        assert!(!has_differences(&before, &after, &missing(false)));
+        assert!(collect_changes(&before, &after, &missing(false)).is_empty());
+        assert_eq!(compare_json_with_options(&before, &after, &missing(false)),
+                   json!({"a": null, "b": [], "c": 1, "d": {"e": {}, "f": null}}));
+
+        let null_only = DiffOptions { missing_as_null: true, ..DiffOptions::default() };
+        assert!(has_differences(&before, &after, &null_only));
+    }
+
+    #[test]
+    fn present_values_are_not_equivalent() {
+        for (before, after) in [(json!({"a": []}), json!({"a": {}})), (json!({"a": null}), json!({"a": []}))] {
+            assert!(has_differences(&before, &after, &missing(true)));
+            let changes = collect_changes(&before, &after, &missing(true));
+            assert_eq!(changes.len(), 1);
+            assert_eq!(changes[0].kind, ChangeKind::Modified);
+        }
+    }
+
+    #[test]
+    fn cosmetic_changes_are_reported_on_request() {
+        let before = json!({"a": null, "b": 1});
+        let after = json!({"b": 1, "c": []});
+
+        assert!(!has_differences(&before, &after, &missing(true)));
+        let kinds: Vec<(String, ChangeKind)> = collect_changes(&before, &after, &missing(true)).into_iter()
+            .map(|change| (change.path, change.kind))
+            .collect();
+        assert_eq!(kinds, vec![("/a".to_string(), ChangeKind::Cosmetic), ("/c".to_string(), ChangeKind::Cosmetic)]);
+        assert_eq!(compare_json_with_options(&before, &after, &missing(true)),
+                   json!({"a": {"$cosmetic": "null => missing"}, "b": 1, "c": {"$cosmetic": "missing => []"}}));
+    }
+}
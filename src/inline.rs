@@ -0,0 +1,247 @@
+//! Differences within modified strings
+//!
+//! Multiline strings are compared line by line and shown as a small unified
+//! diff. In other strings, the common prefix and suffix of the two strings are
+//! set aside first, which keeps single-character edits in long URLs or base64
+//! blobs cheap. The rest is split into words and punctuation, diffed with the
+//! edit distance matrix used for identifier similarity, and replaced words are
+//! narrowed down to the characters that changed.
+
+use crate::core::edit_distance_matrix;
+use crate::unified::{format_hunks, DiffLine};
+
+const RED: &str = "\x1b[1;31m";
+const GREEN: &str = "\x1b[1;32m";
+const RESET: &str = "\x1b[0m";
+
+// Largest distance matrix computed; longer differing parts are shown as a
+// single replacement
+const MAX_MATRIX_CELLS: usize = 1_000_000;
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Render a modified string with its changed parts marked
+///
+/// In plain output the changes are written inline as `"a{-b-}{+c+}d"`; with
+/// `color`, the old and new strings are shown as `"abd" => "acd"` with the
+/// removed and added parts highlighted. Strings without any common part are
+/// shown as a plain `"old" => "new"`.
+pub(crate) fn format_inline_change(before: &str, after: &str, color: bool) -> String {
+    let segments = diff_strings(before, after);
+    if !segments.iter().any(|segment| matches!(segment, Segment::Same(text) if !text.trim().is_empty())) {
+        return format!("\"{}\" => \"{}\"", before, after);
+    }
+
+    if color {
+        let mut old = String::new();
+        let mut new = String::new();
+        for segment in &segments {
+            match segment {
+                Segment::Same(text) => {
+                    old.push_str(text);
+                    new.push_str(text);
+                },
+                Segment::Removed(text) => old.push_str(&format!("{}{}{}", RED, text, RESET)),
+                Segment::Added(text) => new.push_str(&format!("{}{}{}", GREEN, text, RESET)),
+            }
+        }
+        format!("\"{}\" => \"{}\"", old, new)
+    } else {
+        let text: String = segments.iter()
+            .map(|segment| match segment {
+                Segment::Same(text) => text.clone(),
+                Segment::Removed(text) => format!("{{-{}-}}", text),
+                Segment::Added(text) => format!("{{+{}+}}", text),
+            })
+            .collect();
+        format!("\"{}\"", text)
+    }
+}
+
+/// Render a modified multiline string as a unified diff of its lines
+///
+/// The diff is enclosed in `"""` lines: the hunks are indented below the key
+/// at `indent`, and the closing quotes are aligned with the key.
+pub(crate) fn format_line_change(before: &str, after: &str, indent: usize, context: usize) -> String {
+    let before_lines: Vec<&str> = before.lines().collect();
+    let after_lines: Vec<&str> = after.lines().collect();
+    let line = |tag: char, text: &str| DiffLine { tag, text: text.to_string(), path: String::new() };
+
+    let mut lines = Vec::new();
+    if (before_lines.len() + 1) * (after_lines.len() + 1) > MAX_MATRIX_CELLS {
+        lines.extend(before_lines.iter().map(|text| line('-', text)));
+        lines.extend(after_lines.iter().map(|text| line('+', text)));
+    } else {
+        let dp = edit_distance_matrix(&before_lines, &after_lines);
+        let (mut i, mut j) = (before_lines.len(), after_lines.len());
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+
+        // Walk back through the matrix, keeping removed lines before added
+        // lines within each run of changes
+        while i > 0 || j > 0 {
+            if i > 0 && j > 0 && before_lines[i - 1] == after_lines[j - 1] && dp[i][j] == dp[i - 1][j - 1] {
+                lines.append(&mut added);
+                lines.append(&mut removed);
+                lines.push(line(' ', before_lines[i - 1]));
+                i -= 1;
+                j -= 1;
+            } else if i > 0 && j > 0 && dp[i][j] == dp[i - 1][j - 1] + 1 {
+                removed.push(line('-', before_lines[i - 1]));
+                added.push(line('+', after_lines[j - 1]));
+                i -= 1;
+                j -= 1;
+            } else if i > 0 && dp[i][j] == dp[i - 1][j] + 1 {
+                removed.push(line('-', before_lines[i - 1]));
+                i -= 1;
+            } else {
+                added.push(line('+', after_lines[j - 1]));
+                j -= 1;
+            }
+        }
+        lines.append(&mut added);
+        lines.append(&mut removed);
+        lines.reverse();
+    }
+
+    let pad = " ".repeat(indent + 2);
+    let mut result = String::from("\"\"\"\n");
+    for hunk_line in format_hunks(&lines, context).lines() {
+        result.push_str(&pad);
+        result.push_str(hunk_line);
+        result.push('\n');
+    }
+    result.push_str(&" ".repeat(indent));
+    result.push_str("\"\"\"");
+    result
+}
+
+// Segments of the two strings, with each run of changes written as the removed
+// text followed by the added text
+fn diff_strings(before: &str, after: &str) -> Vec<Segment> {
+    let before: Vec<char> = before.chars().collect();
+    let after: Vec<char> = after.chars().collect();
+    let (prefix, suffix) = common_affixes(&before, &after);
+
+    let mut segments = vec![Segment::Same(before[..prefix].iter().collect())];
+    let before_mid = &before[prefix..before.len() - suffix];
+    let after_mid = &after[prefix..after.len() - suffix];
+
+    let before_tokens = tokenize(before_mid);
+    let after_tokens = tokenize(after_mid);
+    if (before_tokens.len() + 1) * (after_tokens.len() + 1) > MAX_MATRIX_CELLS {
+        segments.push(Segment::Removed(before_mid.iter().collect()));
+        segments.push(Segment::Added(after_mid.iter().collect()));
+    } else {
+        segments.extend(diff_tokens(&before_tokens, &after_tokens));
+    }
+
+    segments.push(Segment::Same(before[before.len() - suffix..].iter().collect()));
+    merge(segments)
+}
+
+// Edit script between two token sequences, with replaced words narrowed down
+// to their changed characters
+fn diff_tokens(before: &[&[char]], after: &[&[char]]) -> Vec<Segment> {
+    let dp = edit_distance_matrix(before, after);
+    let mut reversed = Vec::new();
+    let (mut i, mut j) = (before.len(), after.len());
+
+    while i > 0 || j > 0 {
+        if i > 0 && j > 0 && before[i - 1] == after[j - 1] && dp[i][j] == dp[i - 1][j - 1] {
+            reversed.push(Segment::Same(before[i - 1].iter().collect()));
+            i -= 1;
+            j -= 1;
+        } else if i > 0 && j > 0 && dp[i][j] == dp[i - 1][j - 1] + 1 {
+            // Pushed in reverse order
+            let (old, new) = (before[i - 1], after[j - 1]);
+            let (prefix, suffix) = common_affixes(old, new);
+            reversed.push(Segment::Same(old[old.len() - suffix..].iter().collect()));
+            reversed.push(Segment::Added(new[prefix..new.len() - suffix].iter().collect()));
+            reversed.push(Segment::Removed(old[prefix..old.len() - suffix].iter().collect()));
+            reversed.push(Segment::Same(old[..prefix].iter().collect()));
+            i -= 1;
+            j -= 1;
+        } else if i > 0 && dp[i][j] == dp[i - 1][j] + 1 {
+            reversed.push(Segment::Removed(before[i - 1].iter().collect()));
+            i -= 1;
+        } else {
+            reversed.push(Segment::Added(after[j - 1].iter().collect()));
+            j -= 1;
+        }
+    }
+
+    reversed.reverse();
+    reversed
+}
+
+// Join adjacent segments, moving the removed text of each run of changes
+// before its added text and dropping empty segments
+fn merge(segments: Vec<Segment>) -> Vec<Segment> {
+    let mut result = Vec::new();
+    let mut removed = String::new();
+    let mut added = String::new();
+
+    let flush = |removed: &mut String, added: &mut String, result: &mut Vec<Segment>| {
+        if !removed.is_empty() {
+            result.push(Segment::Removed(std::mem::take(removed)));
+        }
+        if !added.is_empty() {
+            result.push(Segment::Added(std::mem::take(added)));
+        }
+    };
+
+    for segment in segments {
+        match segment {
+            Segment::Same(text) if text.is_empty() => {},
+            Segment::Same(text) => {
+                flush(&mut removed, &mut added, &mut result);
+                match result.last_mut() {
+                    Some(Segment::Same(previous)) => previous.push_str(&text),
+                    _ => result.push(Segment::Same(text)),
+                }
+            },
+            Segment::Removed(text) => removed.push_str(&text),
+            Segment::Added(text) => added.push_str(&text),
+        }
+    }
+    flush(&mut removed, &mut added, &mut result);
+
+    result
+}
+
+// Split text into words (runs of letters, digits and underscores) and single
+// other characters
+fn tokenize(chars: &[char]) -> Vec<&[char]> {
+    let is_word = |c: &char| c.is_alphanumeric() || *c == '_';
+    let mut tokens = Vec::new();
+    let mut start = 0;
+
+    while start < chars.len() {
+        let mut end = start + 1;
+        if is_word(&chars[start]) {
+            while end < chars.len() && is_word(&chars[end]) {
+                end += 1;
+            }
+        }
+        tokens.push(&chars[start..end]);
+        start = end;
+    }
+
+    tokens
+}
+
+// Lengths of the common prefix and of the common suffix not overlapping it
+fn common_affixes(a: &[char], b: &[char]) -> (usize, usize) {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    (prefix, suffix)
+}
@@ -0,0 +1,495 @@
+//! Loading documents in formats other than JSON
+//!
+//! Every supported format is converted into a `serde_json::Value` so that it can
+//! be passed to [`compare_json`](crate::compare_json). Parsers for formats other
+//! than JSON are enabled with cargo features:
+//!
+//! * `yaml` - YAML, including multi-document streams
+//! * `toml` - TOML
+//! * `cbor` - CBOR
+//! * `msgpack` - MessagePack
+//! * `bson` - BSON, including `mongodump` files with several documents
+//!
+//! JSONC (comments and trailing commas) is always supported by the lenient
+//! JSON5 parser; the full JSON5 syntax requires the `json5` feature.
+//!
+//! Values of the binary formats that have no JSON equivalent are represented
+//! in MongoDB relaxed Extended JSON: `{"$binary": {"base64": ..., "subType": ...}}`
+//! for byte strings, `{"$date": "<RFC 3339>"}` for timestamps, `{"$oid": ...}`
+//! for ObjectIds and so on. [`format_diff_to_string`](crate::format_diff_to_string)
+//! renders these objects in a compact form.
+
+use serde_json::Value;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::lenient::parse_lenient;
+
+/// Format of an input document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    Json,
+    /// JSON with comments and trailing commas, or JSON5
+    Json5,
+    Yaml,
+    Toml,
+    Cbor,
+    MessagePack,
+    Bson,
+}
+
+impl InputFormat {
+    /// Detect the format of a file from its extension
+    ///
+    /// Returns `None` for unknown or missing extensions.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<InputFormat> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => Some(InputFormat::Json),
+            "jsonc" | "json5" => Some(InputFormat::Json5),
+            "yaml" | "yml" => Some(InputFormat::Yaml),
+            "toml" => Some(InputFormat::Toml),
+            "cbor" => Some(InputFormat::Cbor),
+            "msgpack" | "mpk" => Some(InputFormat::MessagePack),
+            "bson" => Some(InputFormat::Bson),
+            _ => None,
+        }
+    }
+
+    /// Detect the format of a document from its first bytes
+    ///
+    /// The BSON length prefixes are checked first; documents starting with
+    /// printable text are then reported as JSON. Otherwise the CBOR
+    /// self-describe tag and the leading container type byte are checked. Returns `None` if no format could be recognized.
+    pub fn detect(bytes: &[u8]) -> Option<InputFormat> {
+        if is_bson(bytes) {
+            return Some(InputFormat::Bson);
+        }
+        let first = *bytes.iter().find(|b| !b.is_ascii_whitespace())?;
+        if first.is_ascii() && !first.is_ascii_control() {
+            return Some(InputFormat::Json);
+        }
+        if bytes.starts_with(&[0xd9, 0xd9, 0xf7]) {
+            return Some(InputFormat::Cbor);
+        }
+
+        match bytes[0] {
+            // MessagePack fixmap, fixarray, array 16/32 and map 16/32; CBOR
+            // arrays share part of this range
+            0x80..=0x9f | 0xdc..=0xdf => {
+                if cfg!(feature = "cbor") && !cfg!(feature = "msgpack") {
+                    Some(InputFormat::Cbor)
+                } else if parse_msgpack(bytes).is_ok() || !cfg!(feature = "cbor") {
+                    Some(InputFormat::MessagePack)
+                } else {
+                    Some(InputFormat::Cbor)
+                }
+            },
+            // CBOR maps
+            0xa0..=0xbf => Some(InputFormat::Cbor),
+            _ => None,
+        }
+    }
+
+    /// Whether the format is a binary encoding rather than text
+    pub fn is_binary(&self) -> bool {
+        matches!(self, InputFormat::Cbor | InputFormat::MessagePack | InputFormat::Bson)
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(InputFormat::Json),
+            "jsonc" | "json5" => Ok(InputFormat::Json5),
+            "yaml" | "yml" => Ok(InputFormat::Yaml),
+            "toml" => Ok(InputFormat::Toml),
+            "cbor" => Ok(InputFormat::Cbor),
+            "msgpack" | "messagepack" => Ok(InputFormat::MessagePack),
+            "bson" => Ok(InputFormat::Bson),
+            _ => Err(format!(
+                "unknown input format '{}' (expected json, json5, yaml, toml, cbor, msgpack or bson)", s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            InputFormat::Json => "JSON",
+            InputFormat::Json5 => "JSON5",
+            InputFormat::Yaml => "YAML",
+            InputFormat::Toml => "TOML",
+            InputFormat::Cbor => "CBOR",
+            InputFormat::MessagePack => "MessagePack",
+            InputFormat::Bson => "BSON",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Parse a document in the given format into a JSON value
+///
+/// A YAML stream with several documents is returned as an array holding one
+/// element per document.
+///
+/// # Returns
+/// The parsed document, or an error if it is invalid or support for the format
+/// was not compiled in
+pub fn parse_document(text: &str, format: InputFormat) -> Result<Value, String> {
+    match format {
+        InputFormat::Json => serde_json::from_str(text).map_err(|e| e.to_string()),
+        InputFormat::Json5 => parse_lenient(text),
+        InputFormat::Yaml => parse_yaml(text),
+        InputFormat::Toml => parse_toml(text),
+        _ => parse_document_bytes(text.as_bytes(), format),
+    }
+}
+
+/// Parse a document in the given text or binary format into a JSON value
+///
+/// Text formats must be UTF-8 encoded. A BSON file with several documents is
+/// returned as an array holding one element per document.
+pub fn parse_document_bytes(bytes: &[u8], format: InputFormat) -> Result<Value, String> {
+    match format {
+        InputFormat::Cbor => parse_cbor(bytes),
+        InputFormat::MessagePack => parse_msgpack(bytes),
+        InputFormat::Bson => parse_bson(bytes),
+        _ => {
+            let text = std::str::from_utf8(bytes).map_err(|e| format!("invalid UTF-8: {}", e))?;
+            parse_document(text, format)
+        },
+    }
+}
+
+#[cfg(feature = "yaml")]
+fn parse_yaml(text: &str) -> Result<Value, String> {
+    use serde::Deserialize;
+
+    let mut documents = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(text) {
+        let value = serde_yaml::Value::deserialize(document).map_err(|e| e.to_string())?;
+        documents.push(yaml_to_json(value));
+    }
+
+    match documents.len() {
+        0 => Ok(Value::Null),
+        1 => Ok(documents.remove(0)),
+        _ => Ok(Value::Array(documents)),
+    }
+}
+
+#[cfg(not(feature = "yaml"))]
+fn parse_yaml(_text: &str) -> Result<Value, String> {
+    Err("YAML support is not enabled (rebuild with the `yaml` feature)".to_string())
+}
+
+#[cfg(feature = "yaml")]
+fn yaml_to_json(value: serde_yaml::Value) -> Value {
+    use serde_yaml::Value as Yaml;
+
+    match value {
+        Yaml::Null => Value::Null,
+        Yaml::Bool(b) => Value::Bool(b),
+        Yaml::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Value::from(i)
+            } else if let Some(u) = n.as_u64() {
+                Value::from(u)
+            } else {
+                float_to_json(n.as_f64().unwrap_or(f64::NAN))
+            }
+        },
+        Yaml::String(s) => Value::String(s),
+        Yaml::Sequence(seq) => Value::Array(seq.into_iter().map(yaml_to_json).collect()),
+        Yaml::Mapping(map) => {
+            let obj = map.into_iter()
+                .map(|(key, value)| (yaml_key(key), yaml_to_json(value)))
+                .collect();
+            Value::Object(obj)
+        },
+        Yaml::Tagged(tagged) => yaml_to_json(tagged.value),
+    }
+}
+
+// JSON object key for a YAML mapping key, which may be any YAML value
+#[cfg(feature = "yaml")]
+fn yaml_key(key: serde_yaml::Value) -> String {
+    match yaml_to_json(key) {
+        Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+#[cfg(feature = "toml")]
+fn parse_toml(text: &str) -> Result<Value, String> {
+    let table: toml::Table = text.parse().map_err(|e: toml::de::Error| e.to_string())?;
+    Ok(toml_to_json(toml::Value::Table(table)))
+}
+
+#[cfg(not(feature = "toml"))]
+fn parse_toml(_text: &str) -> Result<Value, String> {
+    Err("TOML support is not enabled (rebuild with the `toml` feature)".to_string())
+}
+
+#[cfg(feature = "toml")]
+fn toml_to_json(value: toml::Value) -> Value {
+    use toml::Value as Toml;
+
+    match value {
+        Toml::String(s) => Value::String(s),
+        Toml::Integer(i) => Value::from(i),
+        Toml::Float(f) => float_to_json(f),
+        Toml::Boolean(b) => Value::Bool(b),
+        Toml::Datetime(dt) => Value::String(dt.to_string()),
+        Toml::Array(arr) => Value::Array(arr.into_iter().map(toml_to_json).collect()),
+        Toml::Table(table) => {
+            Value::Object(table.into_iter().map(|(key, value)| (key, toml_to_json(value))).collect())
+        },
+    }
+}
+
+// JSON has no representation for NaN and infinities, so they are kept as strings
+#[cfg(any(feature = "yaml", feature = "toml", feature = "cbor", feature = "msgpack"))]
+fn float_to_json(f: f64) -> Value {
+    serde_json::Number::from_f64(f)
+        .map(Value::Number)
+        .unwrap_or_else(|| Value::String(f.to_string()))
+}
+
+// Whether the bytes are a sequence of length-prefixed BSON documents
+fn is_bson(bytes: &[u8]) -> bool {
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        if rest.len() < 5 {
+            return false;
+        }
+        let len = i32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]);
+        if len < 5 || len as usize > rest.len() || rest[len as usize - 1] != 0 {
+            return false;
+        }
+        rest = &rest[len as usize..];
+    }
+    !bytes.is_empty()
+}
+
+#[cfg(feature = "bson")]
+fn parse_bson(bytes: &[u8]) -> Result<Value, String> {
+    let mut documents = Vec::new();
+    let mut reader = bytes;
+    while !reader.is_empty() {
+        let document = bson::Document::from_reader(&mut reader).map_err(|e| e.to_string())?;
+        documents.push(bson::Bson::Document(document).into_relaxed_extjson());
+    }
+
+    match documents.len() {
+        1 => Ok(documents.remove(0)),
+        _ => Ok(Value::Array(documents)),
+    }
+}
+
+#[cfg(not(feature = "bson"))]
+fn parse_bson(_bytes: &[u8]) -> Result<Value, String> {
+    Err("BSON support is not enabled (rebuild with the `bson` feature)".to_string())
+}
+
+#[cfg(feature = "cbor")]
+fn parse_cbor(bytes: &[u8]) -> Result<Value, String> {
+    let mut reader = bytes;
+    let value: ciborium::Value = ciborium::de::from_reader(&mut reader).map_err(|e| e.to_string())?;
+    if !reader.is_empty() {
+        return Err(format!("{} trailing bytes after the document", reader.len()));
+    }
+    Ok(cbor_to_json(value))
+}
+
+#[cfg(not(feature = "cbor"))]
+fn parse_cbor(_bytes: &[u8]) -> Result<Value, String> {
+    Err("CBOR support is not enabled (rebuild with the `cbor` feature)".to_string())
+}
+
+#[cfg(feature = "cbor")]
+fn cbor_to_json(value: ciborium::Value) -> Value {
+    use ciborium::Value as Cbor;
+
+    match value {
+        Cbor::Null => Value::Null,
+        Cbor::Bool(b) => Value::Bool(b),
+        Cbor::Integer(i) => {
+            let i = i128::from(i);
+            i64::try_from(i).map(Value::from)
+                .or_else(|_| u64::try_from(i).map(Value::from))
+                .unwrap_or_else(|_| Value::String(i.to_string()))
+        },
+        Cbor::Float(f) => float_to_json(f),
+        Cbor::Text(s) => Value::String(s),
+        Cbor::Bytes(bytes) => binary_to_json(&bytes, 0),
+        Cbor::Array(arr) => Value::Array(arr.into_iter().map(cbor_to_json).collect()),
+        Cbor::Map(entries) => {
+            let obj = entries.into_iter()
+                .map(|(key, value)| (key_to_string(cbor_to_json(key)), cbor_to_json(value)))
+                .collect();
+            Value::Object(obj)
+        },
+        // Standard date/time string
+        Cbor::Tag(0, inner) => match *inner {
+            Cbor::Text(s) => serde_json::json!({ "$date": s }),
+            other => cbor_to_json(other),
+        },
+        // Epoch-based date/time
+        Cbor::Tag(1, inner) => {
+            let seconds = match &*inner {
+                Cbor::Integer(i) => Some(i128::from(*i) as f64),
+                Cbor::Float(f) => Some(*f),
+                _ => None,
+            };
+            match seconds {
+                Some(seconds) => {
+                    let nanos = (seconds.fract().abs() * 1e9).round() as u32;
+                    serde_json::json!({ "$date": format_rfc3339(seconds.floor() as i64, nanos) })
+                },
+                None => cbor_to_json(*inner),
+            }
+        },
+        Cbor::Tag(_, inner) => cbor_to_json(*inner),
+        _ => Value::Null,
+    }
+}
+
+#[cfg(feature = "msgpack")]
+fn parse_msgpack(bytes: &[u8]) -> Result<Value, String> {
+    let mut reader = bytes;
+    let value = rmpv::decode::read_value(&mut reader).map_err(|e| e.to_string())?;
+    if !reader.is_empty() {
+        return Err(format!("{} trailing bytes after the document", reader.len()));
+    }
+    Ok(msgpack_to_json(value))
+}
+
+#[cfg(not(feature = "msgpack"))]
+fn parse_msgpack(_bytes: &[u8]) -> Result<Value, String> {
+    Err("MessagePack support is not enabled (rebuild with the `msgpack` feature)".to_string())
+}
+
+#[cfg(feature = "msgpack")]
+fn msgpack_to_json(value: rmpv::Value) -> Value {
+    use rmpv::Value as MsgPack;
+
+    match value {
+        MsgPack::Nil => Value::Null,
+        MsgPack::Boolean(b) => Value::Bool(b),
+        MsgPack::Integer(i) => match (i.as_i64(), i.as_u64()) {
+            (Some(i), _) => Value::from(i),
+            (None, Some(u)) => Value::from(u),
+            (None, None) => Value::Null,
+        },
+        MsgPack::F32(f) => float_to_json(f as f64),
+        MsgPack::F64(f) => float_to_json(f),
+        MsgPack::String(s) => match s.into_str() {
+            Some(s) => Value::String(s),
+            None => Value::Null,
+        },
+        MsgPack::Binary(bytes) => binary_to_json(&bytes, 0),
+        MsgPack::Array(arr) => Value::Array(arr.into_iter().map(msgpack_to_json).collect()),
+        MsgPack::Map(entries) => {
+            let obj = entries.into_iter()
+                .map(|(key, value)| (key_to_string(msgpack_to_json(key)), msgpack_to_json(value)))
+                .collect();
+            Value::Object(obj)
+        },
+        // Timestamp extension
+        MsgPack::Ext(-1, data) => match msgpack_timestamp(&data) {
+            Some((seconds, nanos)) => serde_json::json!({ "$date": format_rfc3339(seconds, nanos) }),
+            None => binary_to_json(&data, 0xff),
+        },
+        // Other extension types keep their type code as the binary subtype
+        MsgPack::Ext(ext_type, data) => binary_to_json(&data, ext_type as u8),
+    }
+}
+
+// Seconds and nanoseconds of a MessagePack timestamp extension
+#[cfg(feature = "msgpack")]
+fn msgpack_timestamp(data: &[u8]) -> Option<(i64, u32)> {
+    match data.len() {
+        4 => Some((u32::from_be_bytes(data.try_into().ok()?) as i64, 0)),
+        8 => {
+            let value = u64::from_be_bytes(data.try_into().ok()?);
+            Some(((value & 0x3_ffff_ffff) as i64, (value >> 34) as u32))
+        },
+        12 => {
+            let nanos = u32::from_be_bytes(data[..4].try_into().ok()?);
+            let seconds = i64::from_be_bytes(data[4..].try_into().ok()?);
+            Some((seconds, nanos))
+        },
+        _ => None,
+    }
+}
+
+// Extended JSON representation of a byte string
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+fn binary_to_json(bytes: &[u8], subtype: u8) -> Value {
+    serde_json::json!({
+        "$binary": {
+            "base64": base64_encode(bytes),
+            "subType": format!("{:02x}", subtype),
+        }
+    })
+}
+
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut result = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+// JSON object key for a map key of a binary format, which may be any value
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+fn key_to_string(key: Value) -> String {
+    match key {
+        Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+// RFC 3339 representation of a point in time given in seconds since the epoch
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+fn format_rfc3339(seconds: i64, nanos: u32) -> String {
+    let days = seconds.div_euclid(86_400);
+    let secs_of_day = seconds.rem_euclid(86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    let time = format!("{:02}:{:02}:{:02}", secs_of_day / 3600, secs_of_day % 3600 / 60, secs_of_day % 60);
+    if nanos == 0 {
+        format!("{:04}-{:02}-{:02}T{}Z", year, month, day, time)
+    } else {
+        let fraction = format!("{:09}", nanos);
+        format!("{:04}-{:02}-{:02}T{}.{}Z", year, month, day, time, fraction.trim_end_matches('0'))
+    }
+}
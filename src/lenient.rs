@@ -0,0 +1,386 @@
+//! Lenient parsing of JSONC / JSON5 documents
+//!
+//! Comments (`//` and `/* */`) and trailing commas are removed before the
+//! document is parsed as JSON. With the `json5` feature, documents that are
+//! still not valid JSON afterwards (unquoted keys, single-quoted strings,
+//! hexadecimal numbers, ...) are parsed as JSON5.
+//!
+//! Comments are collected while scanning and attached to the JSON Pointer path
+//! of the nearest key or array element: a comment on the same line as a value
+//! belongs to that value, any other comment belongs to the key or element that
+//! follows it.
+
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+use crate::changes::escape_pointer;
+
+/// Parse a JSONC or JSON5 document
+///
+/// # Returns
+/// The parsed document, or an error describing why it could not be parsed
+pub fn parse_lenient(text: &str) -> Result<Value, String> {
+    let scanned = scan(text);
+    match serde_json::from_str(&scanned.json) {
+        Ok(value) => Ok(value),
+        Err(e) => parse_json5(text).map_err(|json5_error| json5_error.unwrap_or_else(|| e.to_string())),
+    }
+}
+
+/// Collect the comments of a JSONC or JSON5 document
+///
+/// # Returns
+/// A map from the JSON Pointer path of the key or element each comment is
+/// attached to (`""` for the document itself) to the comment text. Several
+/// comments attached to the same path are joined with newlines.
+pub fn extract_comments(text: &str) -> BTreeMap<String, String> {
+    scan(text).comments
+}
+
+/// Compare the comments of two documents
+///
+/// # Returns
+/// An object keyed by JSON Pointer path containing only the comments that
+/// changed, in the same representation as the result of
+/// [`compare_json`](crate::compare_json): `"old => new"`, `"comment [+]"` or
+/// `"comment [-]"`. It can be rendered with
+/// [`format_diff_to_string`](crate::format_diff_to_string).
+pub fn compare_comments(before: &BTreeMap<String, String>, after: &BTreeMap<String, String>) -> Value {
+    let mut result = Map::new();
+
+    for (path, before_comment) in before {
+        match after.get(path) {
+            Some(after_comment) if after_comment != before_comment => {
+                result.insert(path.clone(), Value::String(format!("{} => {}", before_comment, after_comment)));
+            },
+            Some(_) => {},
+            None => {
+                result.insert(path.clone(), Value::String(format!("{} [-]", before_comment)));
+            },
+        }
+    }
+
+    for (path, after_comment) in after {
+        if !before.contains_key(path) {
+            result.insert(path.clone(), Value::String(format!("{} [+]", after_comment)));
+        }
+    }
+
+    Value::Object(result)
+}
+
+#[cfg(feature = "json5")]
+fn parse_json5(text: &str) -> Result<Value, Option<String>> {
+    json5::from_str(text).map_err(|e| Some(e.to_string()))
+}
+
+#[cfg(not(feature = "json5"))]
+fn parse_json5(_text: &str) -> Result<Value, Option<String>> {
+    Err(None)
+}
+
+// Result of scanning a lenient document
+struct Scanned {
+    // Document with comments and trailing commas removed
+    json: String,
+    comments: BTreeMap<String, String>,
+}
+
+// Container being scanned, with the position inside it
+enum Frame {
+    Object { path: String, expect_key: bool, last_key: Option<String> },
+    Array { path: String, index: Option<usize>, expect_value: bool },
+}
+
+struct Scanner {
+    frames: Vec<Frame>,
+    comments: BTreeMap<String, String>,
+    // Comments waiting for the next key or element
+    pending: Vec<String>,
+    // Path of the key or element most recently started
+    last_path: Option<String>,
+    newline_since_token: bool,
+}
+
+impl Scanner {
+    fn attach(&mut self, path: String, comment: String) {
+        self.comments.entry(path)
+            .and_modify(|existing| {
+                existing.push('\n');
+                existing.push_str(&comment);
+            })
+            .or_insert(comment);
+    }
+
+    fn comment(&mut self, comment: String) {
+        match (&self.last_path, self.newline_since_token) {
+            (Some(path), false) => {
+                let path = path.clone();
+                self.attach(path, comment);
+            },
+            _ => self.pending.push(comment),
+        }
+    }
+
+    // A key or array element starts at `path`
+    fn start(&mut self, path: String) {
+        for comment in std::mem::take(&mut self.pending) {
+            self.attach(path.clone(), comment);
+        }
+        self.last_path = Some(path);
+    }
+
+    // Called for every token that is not whitespace or a comment
+    fn token(&mut self, c: char) {
+        self.newline_since_token = false;
+
+        // Start of the document value
+        if self.frames.is_empty() && self.last_path.is_none() {
+            self.start(String::new());
+        }
+
+        // Start of a value inside an array
+        if let Some(Frame::Array { path, index, expect_value }) = self.frames.last_mut() {
+            if *expect_value && c != ']' && c != ',' {
+                *expect_value = false;
+                let next = index.map_or(0, |i| i + 1);
+                *index = Some(next);
+                let element_path = format!("{}/{}", path, next);
+                self.start(element_path);
+            }
+        }
+
+        match c {
+            '{' | '[' => {
+                let path = self.last_path.clone().unwrap_or_default();
+                self.frames.push(if c == '{' {
+                    Frame::Object { path: path.clone(), expect_key: true, last_key: None }
+                } else {
+                    Frame::Array { path: path.clone(), index: None, expect_value: true }
+                });
+                self.last_path = Some(path);
+            },
+            '}' | ']' => {
+                if let Some(frame) = self.frames.pop() {
+                    // Comments at the end of a container belong to its last entry
+                    let (path, last) = match frame {
+                        Frame::Object { path, last_key, .. } => {
+                            let last = last_key.map(|key| format!("{}/{}", path, escape_pointer(&key)));
+                            (path, last)
+                        },
+                        Frame::Array { path, index, .. } => {
+                            let last = index.map(|i| format!("{}/{}", path, i));
+                            (path, last)
+                        },
+                    };
+                    let target = last.unwrap_or_else(|| path.clone());
+                    for comment in std::mem::take(&mut self.pending) {
+                        self.attach(target.clone(), comment);
+                    }
+                    self.last_path = Some(path);
+                }
+            },
+            ',' => match self.frames.last_mut() {
+                Some(Frame::Object { expect_key, .. }) => *expect_key = true,
+                Some(Frame::Array { expect_value, .. }) => *expect_value = true,
+                None => {},
+            },
+            _ => {},
+        }
+    }
+
+    // Called with the contents of an object key
+    fn key(&mut self, key: &str) {
+        if let Some(Frame::Object { path, expect_key, last_key }) = self.frames.last_mut() {
+            *expect_key = false;
+            *last_key = Some(key.to_string());
+            let key_path = format!("{}/{}", path, escape_pointer(key));
+            self.start(key_path);
+        }
+    }
+
+    fn expects_key(&self) -> bool {
+        matches!(self.frames.last(), Some(Frame::Object { expect_key: true, .. }))
+    }
+}
+
+fn scan(text: &str) -> Scanned {
+    let mut scanner = Scanner {
+        frames: Vec::new(),
+        comments: BTreeMap::new(),
+        pending: Vec::new(),
+        last_path: None,
+        newline_since_token: true,
+    };
+    let mut json = String::with_capacity(text.len());
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '"' | '\'' => {
+                // Copy the string verbatim
+                let start = i;
+                i += 1;
+                while i < chars.len() && chars[i] != c {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                let end = (i + 1).min(chars.len());
+                let literal: String = chars[start..end].iter().collect();
+                if scanner.expects_key() {
+                    scanner.token(c);
+                    scanner.key(&unquote(&literal));
+                } else {
+                    scanner.token(c);
+                }
+                json.push_str(&literal);
+                i = end;
+                continue;
+            },
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                let start = i;
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                let comment: String = chars[start..i].iter().collect();
+                scanner.comment(comment.trim_end().to_string());
+                continue;
+            },
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                let start = i;
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i = (i + 2).min(chars.len());
+                let comment: String = chars[start..i].iter().collect();
+                // Keep line numbers intact for parse errors
+                json.extend(comment.chars().filter(|&ch| ch == '\n'));
+                scanner.comment(comment);
+                continue;
+            },
+            '\n' => {
+                scanner.newline_since_token = true;
+                json.push(c);
+            },
+            c if c.is_whitespace() => json.push(c),
+            _ => {
+                if scanner.expects_key() && c != '}' && c != ',' {
+                    // Unquoted JSON5 key
+                    let start = i;
+                    while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$') {
+                        i += 1;
+                    }
+                    if i > start {
+                        let key: String = chars[start..i].iter().collect();
+                        scanner.token(c);
+                        scanner.key(&key);
+                        json.push_str(&Value::String(key).to_string());
+                        continue;
+                    }
+                }
+                scanner.token(c);
+                json.push(c);
+            },
+        }
+        i += 1;
+    }
+
+    let root_comments = std::mem::take(&mut scanner.pending);
+    for comment in root_comments {
+        scanner.attach(String::new(), comment);
+    }
+
+    Scanned {
+        json: remove_trailing_commas(&json),
+        comments: scanner.comments,
+    }
+}
+
+// Remove commas that are directly followed (ignoring whitespace) by a closing
+// bracket or brace
+fn remove_trailing_commas(json: &str) -> String {
+    let chars: Vec<char> = json.chars().collect();
+    let mut result = String::with_capacity(json.len());
+    let mut in_string: Option<char> = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if let Some(quote) = in_string {
+            result.push(c);
+            if c == '\\' {
+                if let Some(&next) = chars.get(i + 1) {
+                    result.push(next);
+                    i += 1;
+                }
+            } else if c == quote {
+                in_string = None;
+            }
+        } else if c == '"' || c == '\'' {
+            in_string = Some(c);
+            result.push(c);
+        } else if c == ',' {
+            let next = chars[i + 1..].iter().find(|ch| !ch.is_whitespace());
+            if !matches!(next, Some('}') | Some(']')) {
+                result.push(c);
+            }
+        } else {
+            result.push(c);
+        }
+        i += 1;
+    }
+
+    result
+}
+
+// Contents of a quoted key literal
+fn unquote(literal: &str) -> String {
+    if literal.starts_with('"') {
+        if let Ok(key) = serde_json::from_str::<String>(literal) {
+            return key;
+        }
+    }
+    literal.trim_matches(|c| c == '"' || c == '\'').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn comments_and_trailing_commas_are_ignored() {
+        let text = "{\n  // note\n  \"url\": \"http://example.com\", /* port */ \"port\": 80,\n  \"list\": [1, 2,],\n}";
+        assert_eq!(parse_lenient(text).unwrap(), json!({"url": "http://example.com", "port": 80, "list": [1, 2]}));
+    }
+
+    #[test]
+    fn comments_are_attached_to_the_nearest_key_or_element() {
+        let text = "// header\n{\n  // about a\n  \"a\": 1, // after a\n  \"b/c\": [\n    // first\n    1,\n    2 // second\n  ],\n  \"d\": {}\n  // trailing\n}";
+        let comments = extract_comments(text);
+        let expected: BTreeMap<String, String> = [
+            ("", "// header"),
+            ("/a", "// about a\n// after a"),
+            ("/b~1c/0", "// first"),
+            ("/b~1c/1", "// second"),
+            ("/d", "// trailing"),
+        ].into_iter().map(|(path, comment)| (path.to_string(), comment.to_string())).collect();
+        assert_eq!(comments, expected);
+    }
+
+    #[test]
+    fn only_changed_comments_are_compared() {
+        let before = extract_comments("{\n  \"a\": 1, // one\n  \"b\": 2, // two\n  \"c\": 3 // three\n}");
+        let after = extract_comments("{\n  \"a\": 1, // one\n  \"b\": 2, // 2\n  \"c\": 3,\n  \"d\": 4 // four\n}");
+        assert_eq!(compare_comments(&before, &after), json!({
+            "/b": "// two => // 2",
+            "/c": "// three [-]",
+            "/d": "// four [+]",
+        }));
+    }
+}
@@ -10,16 +10,68 @@
 //! - Detection of deleted elements (marked with `[-]`)
 //! - Display of value changes in the format `"old" => "new"`
 //! - Recursive comparison of nested objects and arrays
+//! - Word- and character-level highlighting of changes within long strings
+//! - Line-by-line diffs of modified multiline strings
 //! - Intelligent matching of similar elements using Levenshtein distance
+//! - Indexed array matching that scales to large arrays (see [`DiffOptions`])
+//! - Side-by-side two-column rendering with optional color
+//! - Unified diff of the aligned pretty-printed documents
+//! - Markdown reports for pull requests and wikis
+//! - Flat listing of changes, one JSON Pointer path per line
+//! - Change statistics: counts by kind and top-level key, most changed and deepest subtrees
+//! - Streaming comparison of documents too large to load into memory
+//! - Recursive comparison of directory trees, file by file
+//! - Record-level comparison of JSON Lines / NDJSON documents
+//! - YAML and TOML input (with the `yaml` and `toml` features)
+//! - CBOR, MessagePack and BSON input (with the `cbor`, `msgpack` and `bson` features)
+//! - Lenient JSONC / JSON5 input, with optional reporting of comment changes
+//! - Recursive comparison of JSON documents embedded in string values
+//! - Comparison of date-time strings by instant, with an optional tolerance
+//! - Custom comparators selected by path pattern or predicate
+//! - Ordered, set and multiset semantics for arrays selected by path
+//! - Missing keys treated like `null` or empty containers, optionally reported as cosmetic changes
+//! - Case, whitespace, Unicode and line-ending normalization of strings before comparison
+//! - Loose comparison of numbers and booleans written as strings, such as `"42"` and `42`
 
+mod align;
+mod changes;
+mod comparator;
 mod core;
+mod datetime;
+mod dir;
+mod equivalence;
+mod inline;
+mod input;
+mod lenient;
+mod markdown;
+mod matching;
+mod ndjson;
+mod options;
+mod pattern;
+mod side_by_side;
+mod stats;
+mod stream;
+mod unified;
 
 // Conditionally include Python bindings module
 #[cfg(feature = "python-bindings")]
 mod python;
 
 // Re-export the main functions
-pub use core::{compare_json, format_diff_to_string};
+pub use core::{compare_json, compare_json_with_options, format_diff_to_string, format_diff_with_options};
+pub use changes::{collect_changes, format_changes_as_paths, has_differences, Change, ChangeKind};
+pub use comparator::{Comparator, Comparison};
+pub use dir::{compare_dirs, format_dir_diff, DirFilter, FileChange};
+pub use input::{parse_document, parse_document_bytes, InputFormat};
+pub use lenient::{compare_comments, extract_comments, parse_lenient};
+pub use markdown::{format_markdown_report, MarkdownOptions};
+pub use options::{ArraySemantics, DiffOptions, FormatOptions, Normalization};
+pub use pattern::PathPattern;
+pub use side_by_side::format_side_by_side;
+pub use unified::format_unified_diff;
+pub use stats::{ChangeCounts, DiffStats};
+pub use stream::compare_json_streaming;
+pub use ndjson::{compare_ndjson, format_ndjson_diff, parse_ndjson, NdjsonRecord, RecordChange};
 
 // Export the Python module if python-bindings feature is enabled
 #[cfg(feature = "python-bindings")]
@@ -31,5 +83,6 @@ fn json_diff_view(py: Python<'_>, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(python::compare_json_strings, py)?)?;
     m.add_function(wrap_pyfunction!(python::compare_json_files, py)?)?;
     m.add_function(wrap_pyfunction!(python::compare_json_values, py)?)?;
+    m.add_function(wrap_pyfunction!(python::compare_json_stats, py)?)?;
     Ok(())
 }
\ No newline at end of file
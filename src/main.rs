@@ -1,62 +1,979 @@
 //! Command-line interface for JSON Diff View
 
-use json_diff_view::{compare_json, format_diff_to_string};
-use std::fs;
+use json_diff_view::{
+    collect_changes, compare_comments, compare_dirs, compare_json_streaming, compare_json_with_options, compare_ndjson, extract_comments,
+    format_changes_as_paths, format_diff_to_string, format_diff_with_options, format_dir_diff, format_markdown_report, format_side_by_side, format_unified_diff, format_ndjson_diff, has_differences, parse_document_bytes, parse_ndjson,
+    ArraySemantics, ChangeKind, DiffOptions, FormatOptions, MarkdownOptions, DiffStats, DirFilter, FileChange, InputFormat, Normalization, PathPattern,
+    RecordChange,
+};
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
 use std::error::Error;
-use clap::{Parser, ArgAction};
-use serde_json::Value;
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Read, Write};
+use std::path::Path;
+use std::process::{Command as Process, ExitCode};
+use std::time::Duration;
+use clap::{ArgAction, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap::error::ErrorKind;
+use serde_json::{json, Value};
 
 /// A tool for visually displaying differences between JSON files
+///
+/// Exits with status 0 if the inputs are identical, 1 if they differ and 2 on error.
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
-    /// Path to the original JSON file
-    #[arg(index = 1)]
-    before_file: String,
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    /// Path to the modified JSON file
-    #[arg(index = 2)]
-    after_file: String,
+    /// Original and modified files or directories; `-` reads one of them from standard input.
+    /// The seven or nine arguments git passes to GIT_EXTERNAL_DIFF are also accepted
+    #[arg(value_name = "FILE", num_args = 0..=9)]
+    files: Vec<String>,
+
+    /// Use this JSON text as the original document instead of a file
+    #[arg(long, value_name = "JSON")]
+    before_string: Option<String>,
+
+    /// Use this JSON text as the modified document instead of a file
+    #[arg(long, value_name = "JSON")]
+    after_string: Option<String>,
 
     /// Output raw JSON diff without formatting
-    #[arg(short, long, action = ArgAction::SetTrue)]
+    #[arg(short, long, action = ArgAction::SetTrue, conflicts_with = "stream")]
     raw: bool,
 
+    /// Read both files incrementally, writing changes as they are found
+    #[arg(long, action = ArgAction::SetTrue)]
+    stream: bool,
+
+    /// Number of unmatched array elements kept in memory per side in streaming mode
+    #[arg(long, value_name = "N", requires = "stream", value_parser = parse_window)]
+    stream_window: Option<usize>,
+
+    /// Treat both files as JSON Lines / NDJSON (the default for .ndjson and .jsonl files)
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "stream")]
+    ndjson: bool,
+
+    /// Input format of both files: json, json5, yaml, toml, cbor, msgpack or bson
+    /// (detected from the file extension or contents by default)
+    #[arg(long, value_name = "FORMAT", conflicts_with = "stream")]
+    from: Option<InputFormat>,
+
+    /// Accept comments, trailing commas and JSON5 syntax in JSON files (the default for .jsonc and .json5 files)
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "stream")]
+    lenient: bool,
+
+    /// Also report comments that changed, by the path of the key they are attached to
+    #[arg(long, action = ArgAction::SetTrue)]
+    show_comments: bool,
+
+    /// Only compare files matching this glob when comparing directories (may be repeated)
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Skip files and directories matching this glob when comparing directories (may be repeated)
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Field identifying NDJSON records (a field name or a JSON Pointer such as /meta/id)
+    #[arg(long, value_name = "FIELD")]
+    key: Option<String>,
+
+    /// Output format of the diff
+    #[arg(long, value_enum, default_value_t = OutputFormat::Tree, conflicts_with = "stream")]
+    format: OutputFormat,
+
+    /// Total width of the side-by-side view (defaults to the terminal width)
+    #[arg(long, value_name = "N")]
+    width: Option<usize>,
+
+    /// Number of unchanged lines around each change in unified diffs and multiline strings
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    context: usize,
+
+    /// Show only the changed words and characters of modified strings at least N characters long
+    #[arg(long, value_name = "N", default_value_t = 32)]
+    inline_min_len: usize,
+
+    /// Highlight changes with colors
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Print change statistics instead of the diff
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "stream")]
+    stat: bool,
+
+    /// Print nothing; only set the exit status
+    #[arg(short, long, action = ArgAction::SetTrue, conflicts_with = "brief")]
+    quiet: bool,
+
+    /// Only print a one-line summary if the inputs differ
+    #[arg(long, action = ArgAction::SetTrue)]
+    brief: bool,
+
     /// Add auto-incremental index field to objects in arrays
     #[arg(long, action = ArgAction::SetTrue)]
     add_idx: bool,
+
+    /// Compare arrays longer than this position by position instead of matching elements
+    #[arg(long, value_name = "N")]
+    max_match_len: Option<usize>,
+
+    /// Parse JSON objects and arrays encoded in strings and compare their contents, in all strings
+    /// or only at paths matching PATTERN, such as /events/*/payload (may be repeated)
+    #[arg(long, value_name = "PATTERN", num_args = 0..=1, require_equals = true, default_missing_value = "/**",
+          conflicts_with = "stream")]
+    embedded_json: Vec<PathPattern>,
+
+    /// Compare RFC 3339 date-time strings as instants, in all strings or only at paths matching
+    /// PATTERN (may be repeated)
+    #[arg(long, value_name = "PATTERN", num_args = 0..=1, require_equals = true, default_missing_value = "/**",
+          conflicts_with = "stream")]
+    datetime: Vec<PathPattern>,
+
+    /// Treat date-times at most this far apart as equal, e.g. 500ms, 30s or 1h30m
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, requires = "datetime")]
+    datetime_tolerance: Option<Duration>,
+
+    /// Compare arrays at paths matching PATTERN position by position (may be repeated)
+    #[arg(long, value_name = "PATTERN", conflicts_with = "stream")]
+    ordered: Vec<PathPattern>,
+
+    /// Compare arrays at paths matching PATTERN as sets, ignoring order and duplicates (may be repeated)
+    #[arg(long, value_name = "PATTERN", conflicts_with = "stream")]
+    unordered: Vec<PathPattern>,
+
+    /// Compare arrays at paths matching PATTERN as multisets, ignoring order (may be repeated)
+    #[arg(long, value_name = "PATTERN", conflicts_with = "stream")]
+    multiset: Vec<PathPattern>,
+
+    /// Treat a missing object key as equal to null
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "stream")]
+    missing_as_null: bool,
+
+    /// Treat a missing object key as equal to an empty array or object
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "stream")]
+    missing_as_empty: bool,
+
+    /// Normalize strings before comparing them, in all strings or only at paths matching PATTERN:
+    /// comma-separated case, trim, whitespace, nfc, nfkc or line-endings (may be repeated)
+    #[arg(long, value_name = "[PATTERN=]NAMES", value_parser = parse_normalize, conflicts_with = "stream")]
+    normalize: Vec<(PathPattern, Vec<Normalization>)>,
+
+    /// Treat numbers and booleans written as strings as equal to their values, such as "42" and 42,
+    /// in all values or only at paths matching PATTERN (may be repeated)
+    #[arg(long, value_name = "PATTERN", num_args = 0..=1, require_equals = true, default_missing_value = "/**",
+          conflicts_with = "stream")]
+    coerce_types: Vec<PathPattern>,
+
+    /// Report values only equal because of the options above as cosmetic changes
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "stream")]
+    report_cosmetic: bool,
+
+    /// Read comparison settings from a JSON file; command-line options take precedence
+    #[arg(long, value_name = "FILE")]
+    config: Option<String>,
+
+    /// Number of threads used for diffing (defaults to the number of CPUs)
+    #[cfg(feature = "parallel")]
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum OutputFormat {
+    /// The whole document with changes marked inline
+    Tree,
+    /// One change per line with its JSON Pointer path
+    Paths,
+    /// Both documents in two columns, aligned by matched values
+    SideBySide,
+    /// Unified diff of both documents, pretty-printed with aligned structure
+    Unified,
+    /// Markdown report with summary tables and the full diff in a collapsible block
+    Markdown,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum ColorChoice {
+    /// Use colors when writing to a terminal and NO_COLOR is not set
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Auto => io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Compare files changed between two git revisions, or between a revision and the working tree
+    Git {
+        /// Original revision
+        rev1: String,
+
+        /// Modified revision (the working tree by default)
+        rev2: Option<String>,
+
+        /// Files or directories to compare (all changed files with a known format by default)
+        #[arg(last = true, value_name = "PATH")]
+        paths: Vec<String>,
+    },
+
+    /// Print a file as JSON with sorted keys, for use as a git textconv filter
+    Textconv {
+        /// File to convert; `-` reads standard input
+        #[arg(value_name = "FILE")]
+        file: String,
+    },
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() -> ExitCode {
     // Parse command line arguments using clap
     let cli = Cli::parse();
     
-    // Read and parse JSON files
-    let before_text = fs::read_to_string(&cli.before_file)
-        .map_err(|e| format!("Failed to read file {}: {}", cli.before_file, e))?;
+    // Exit codes follow diff(1): 0 identical, 1 different, 2 trouble
+    match run(&cli) {
+        Ok(false) => ExitCode::SUCCESS,
+        Ok(true) => ExitCode::from(1),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(2)
+        },
+    }
+}
+
+// Compare the inputs and print the result, returning whether they differ
+fn run(cli: &Cli) -> Result<bool, Box<dyn Error>> {
+    #[cfg(feature = "parallel")]
+    if let Some(jobs) = cli.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()
+            .map_err(|e| format!("Failed to start {} worker threads: {}", jobs, e))?;
+    }
     
-    let after_text = fs::read_to_string(&cli.after_file)
-        .map_err(|e| format!("Failed to read file {}: {}", cli.after_file, e))?;
+    let mut options = DiffOptions {
+        add_idx: cli.add_idx,
+        embedded_json: cli.embedded_json.clone(),
+        datetime: cli.datetime.clone(),
+        coerce_types: cli.coerce_types.clone(),
+        missing_as_null: cli.missing_as_null,
+        missing_as_empty: cli.missing_as_empty,
+        report_cosmetic: cli.report_cosmetic,
+        ..DiffOptions::default()
+    };
+    if let Some(tolerance) = cli.datetime_tolerance {
+        options.datetime_tolerance = tolerance;
+    }
+    for (patterns, semantics) in [
+        (&cli.ordered, ArraySemantics::Ordered),
+        (&cli.unordered, ArraySemantics::Unordered),
+        (&cli.multiset, ArraySemantics::Multiset),
+    ] {
+        options.array_semantics.extend(patterns.iter().map(|pattern| (pattern.clone(), semantics)));
+    }
+    for (pattern, normalizations) in &cli.normalize {
+        options.normalizations.extend(normalizations.iter().map(|normalization| (pattern.clone(), *normalization)));
+    }
+    if let Some(config) = &cli.config {
+        if cli.stream {
+            return Err("--config cannot be used with --stream".into());
+        }
+        apply_config(config, &mut options)?;
+    }
+    if let Some(max_match_len) = cli.max_match_len {
+        options.max_match_len = max_match_len;
+    }
+    if let Some(stream_window) = cli.stream_window {
+        options.stream_window = stream_window;
+    }
+    
+    match &cli.command {
+        Some(Command::Git { rev1, rev2, paths }) => {
+            return compare_revisions(cli, rev1, rev2.as_deref(), paths, &options);
+        },
+        Some(Command::Textconv { file }) => return textconv(cli, &Source::from_arg(file)),
+        None => {},
+    }
+    if let Some(args) = external_diff_args(&cli.files) {
+        return external_diff(cli, args, &options);
+    }
     
-    let before: Value = serde_json::from_str(&before_text)
-        .map_err(|e| format!("Failed to parse JSON from {}: {}", cli.before_file, e))?;
+    let (before_source, after_source) = input_sources(cli);
     
-    let after: Value = serde_json::from_str(&after_text)
-        .map_err(|e| format!("Failed to parse JSON from {}: {}", cli.after_file, e))?;
+    if before_source.is_dir() || after_source.is_dir() {
+        return compare_directories(cli, &before_source, &after_source, &options);
+    }
     
-    // Compare JSON structures
-    let result = compare_json(&before, &after, Some(cli.add_idx));
+    if cli.stream {
+        let before_reader = before_source.reader()
+            .map_err(|e| format!("Failed to read {}: {}", before_source, e))?;
+        let after_reader = after_source.reader()
+            .map_err(|e| format!("Failed to read {}: {}", after_source, e))?;
+        
+        let differs = if cli.quiet || cli.brief {
+            compare_json_streaming(before_reader, after_reader, &options, &mut io::sink())
+        } else {
+            let stdout = io::stdout();
+            let mut out = BufWriter::new(stdout.lock());
+            let differs = compare_json_streaming(before_reader, after_reader, &options, &mut out);
+            out.flush()?;
+            differs
+        }.map_err(|e| e.to_string())?;
+        print_brief(cli, &before_source, &after_source, differs);
+        return Ok(differs);
+    }
     
-    // Format and output the result
-    if cli.raw {
-        // Output raw JSON
-        let json_str = serde_json::to_string_pretty(&result)?;
-        println!("{}", json_str);
+    compare_sources(cli, &before_source, &after_source, &options)
+}
+
+// Read, parse and compare two inputs and print the result, returning whether
+// they differ
+fn compare_sources(cli: &Cli, before_source: &Source, after_source: &Source,
+                   options: &DiffOptions) -> Result<bool, Box<dyn Error>> {
+    // Read and parse inputs
+    let before_data = before_source.read()
+        .map_err(|e| format!("Failed to read {}: {}", before_source, e))?;
+    
+    let after_data = after_source.read()
+        .map_err(|e| format!("Failed to read {}: {}", after_source, e))?;
+    
+    if cli.ndjson || before_source.is_ndjson() || after_source.is_ndjson() {
+        check_tree_output(cli, "with NDJSON input")?;
+        let before_text = String::from_utf8_lossy(&before_data);
+        let after_text = String::from_utf8_lossy(&after_data);
+        
+        let before = parse_ndjson(&before_text)
+            .map_err(|e| format!("Failed to parse NDJSON from {}: {}", before_source, e))?;
+        
+        let after = parse_ndjson(&after_text)
+            .map_err(|e| format!("Failed to parse NDJSON from {}: {}", after_source, e))?;
+        
+        let changes = compare_ndjson(&before, &after, cli.key.as_deref(), options);
+        let differs = !changes.is_empty();
+        if cli.quiet || cli.brief {
+            print_brief(cli, before_source, after_source, differs);
+        } else if cli.raw {
+            let json_str = serde_json::to_string_pretty(&changes_to_json(&changes))?;
+            println!("{}", json_str);
+        } else {
+            print!("{}", format_ndjson_diff(&changes));
+        }
+        return Ok(differs);
+    }
+    
+    let before = parse_source(cli, before_source, &before_data)?;
+    let after = parse_source(cli, after_source, &after_data)?;
+    
+    // A file missing on one side compares as an empty document of the other's type
+    let (before, after) = match (before, after) {
+        (Some(before), Some(after)) => (before, after),
+        (None, Some(after)) => (empty_like(&after), after),
+        (Some(before), None) => (before.clone(), empty_like(&before)),
+        (None, None) => (Value::Null, Value::Null),
+    };
+    
+    let differs = has_differences(&before, &after, options);
+    if cli.quiet || cli.brief {
+        print_brief(cli, before_source, after_source, differs);
+        return Ok(differs);
+    }
+    
+    if cli.stat {
+        let stats = DiffStats::compute(&before, &after, options);
+        if cli.raw {
+            println!("{}", serde_json::to_string_pretty(&stats.to_json())?);
+        } else {
+            println!("{}", stats);
+        }
+        return Ok(differs);
+    }
+    
+    match cli.format {
+        OutputFormat::Tree => {
+            // Compare JSON structures
+            let result = compare_json_with_options(&before, &after, options);
+            
+            // Format and output the result
+            if cli.raw {
+                // Output raw JSON
+                let json_str = serde_json::to_string_pretty(&result)?;
+                println!("{}", json_str);
+            } else {
+                // Format with special diff formatting
+                let format_options = FormatOptions {
+                    color: cli.color.enabled(),
+                    inline_diff_min_len: Some(cli.inline_min_len),
+                    multiline_context: Some(cli.context),
+                };
+                let formatted_output = format_diff_with_options(&result, 0, &format_options);
+                println!("{}", formatted_output);
+            }
+        },
+        OutputFormat::Paths => {
+            let changes = collect_changes(&before, &after, options);
+            if cli.raw {
+                let entries: Vec<Value> = changes.iter()
+                    .map(|change| {
+                        let mut entry = json!({ "path": change.path });
+                        let kind = match &change.kind {
+                            ChangeKind::Added => "added",
+                            ChangeKind::Removed => "removed",
+                            ChangeKind::Modified => "modified",
+                            ChangeKind::Moved { from } => {
+                                entry["from"] = json!(from);
+                                "moved"
+                            },
+                            ChangeKind::Cosmetic => "cosmetic",
+                        };
+                        entry["change"] = json!(kind);
+                        if let Some(value) = &change.before {
+                            entry["before"] = value.clone();
+                        }
+                        if let Some(value) = &change.after {
+                            entry["after"] = value.clone();
+                        }
+                        entry
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&entries)?);
+            } else {
+                print!("{}", format_changes_as_paths(&changes));
+            }
+        },
+        OutputFormat::SideBySide => {
+            let width = cli.width.unwrap_or_else(terminal_width);
+            print!("{}", format_side_by_side(&before, &after, options, width, cli.color.enabled()));
+        },
+        OutputFormat::Unified => {
+            let diff = format_unified_diff(&before, &after, options, before_source.name(), after_source.name(),
+                                           cli.context);
+            print!("{}", diff);
+        },
+        OutputFormat::Markdown => {
+            print!("{}", format_markdown_report(&before, &after, options, &MarkdownOptions::default()));
+        },
+    }
+    
+    if cli.show_comments {
+        let before_comments = extract_comments(&String::from_utf8_lossy(&before_data));
+        let after_comments = extract_comments(&String::from_utf8_lossy(&after_data));
+        let comment_changes = compare_comments(&before_comments, &after_comments);
+        if comment_changes.as_object().is_some_and(|changes| !changes.is_empty()) {
+            println!("\nComment changes:");
+            println!("{}", format_diff_to_string(&comment_changes, 0));
+        }
+    }
+    
+    Ok(differs)
+}
+
+// Width of the terminal, or of the COLUMNS variable when not writing to one
+fn terminal_width() -> usize {
+    terminal_size::terminal_size()
+        .map(|(terminal_size::Width(width), _)| width as usize)
+        .or_else(|| env::var("COLUMNS").ok()?.parse().ok())
+        .unwrap_or(80)
+}
+
+// Fail if an output option that needs a single pair of JSON documents was given
+// in a mode that compares several
+fn check_tree_output(cli: &Cli, mode: &str) -> Result<(), String> {
+    if cli.stat {
+        return Err(format!("--stat cannot be used {}", mode));
+    }
+    if cli.format != OutputFormat::Tree {
+        let name = cli.format.to_possible_value().map(|value| value.get_name().to_string()).unwrap_or_default();
+        return Err(format!("--format {} cannot be used {}", name, mode));
+    }
+    Ok(())
+}
+
+// Parse an input in its detected format; `None` for a missing file
+fn parse_source(cli: &Cli, source: &Source, data: &[u8]) -> Result<Option<Value>, String> {
+    if let Source::Absent = source {
+        return Ok(None);
+    }
+    let format = input_format(source, data, cli.from, cli.lenient);
+    parse_document_bytes(data, format)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse {} from {}: {}", format, source, e))
+}
+
+// Empty object or array standing in for a missing document
+fn empty_like(value: &Value) -> Value {
+    match value {
+        Value::Object(_) => json!({}),
+        Value::Array(_) => json!([]),
+        _ => Value::Null,
+    }
+}
+
+// Compare two directory trees file by file
+fn compare_directories(cli: &Cli, before: &Source, after: &Source, options: &DiffOptions) -> Result<bool, Box<dyn Error>> {
+    let (Some(before_dir), Some(after_dir)) = (before.path().filter(|_| before.is_dir()),
+                                               after.path().filter(|_| after.is_dir())) else {
+        return Err("a directory can only be compared with another directory".into());
+    };
+    if cli.stream {
+        return Err("--stream cannot be used to compare directories".into());
+    }
+    if cli.from.is_some() || cli.lenient {
+        // The format of each file is detected from its extension or contents
+        return Err("--from and --lenient cannot be used to compare directories".into());
+    }
+    check_tree_output(cli, "to compare directories")?;
+    
+    let filter = DirFilter::new(&cli.include, &cli.exclude)?;
+    let changes = compare_dirs(before_dir, after_dir, &filter, options)?;
+    let differs = changes.iter().any(|change| !matches!(change, FileChange::Unchanged { .. }));
+    
+    if cli.quiet {
+        // Only the exit status
+    } else if cli.brief {
+        // Same wording as `diff -rq`
+        for change in &changes {
+            let path = change.path();
+            match change {
+                FileChange::Added { .. } => println!("Only in {}", in_dir(after_dir, path)),
+                FileChange::Removed { .. } => println!("Only in {}", in_dir(before_dir, path)),
+                FileChange::Modified { .. } => {
+                    println!("Files {}/{} and {}/{} differ", before_dir.trim_end_matches('/'), path,
+                             after_dir.trim_end_matches('/'), path)
+                },
+                _ => {},
+            }
+        }
+    } else if cli.raw {
+        let entries: Vec<Value> = changes.iter()
+            .filter_map(|change| match change {
+                FileChange::Added { path } => Some(json!({ "change": "added", "path": path })),
+                FileChange::Removed { path } => Some(json!({ "change": "removed", "path": path })),
+                FileChange::Modified { path, diff } => Some(json!({ "change": "modified", "path": path, "diff": diff })),
+                FileChange::Failed { path, error } => Some(json!({ "change": "failed", "path": path, "error": error })),
+                FileChange::Unchanged { .. } => None,
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&entries)?);
     } else {
-        // Format with special diff formatting
-        let formatted_output = format_diff_to_string(&result, 0);
-        println!("{}", formatted_output);
+        print!("{}", format_dir_diff(&changes));
+    }
+    
+    let failed = changes.iter().filter(|change| matches!(change, FileChange::Failed { .. })).count();
+    if failed > 0 {
+        let files = if failed == 1 { "file" } else { "files" };
+        return Err(format!("{} {} could not be compared", failed, files).into());
+    }
+    Ok(differs)
+}
+
+// `dir/sub: name` location of a file only present in one directory, as printed by diff(1)
+fn in_dir(dir: &str, path: &str) -> String {
+    let dir = dir.trim_end_matches('/');
+    match path.rsplit_once('/') {
+        Some((parent, name)) => format!("{}/{}: {}", dir, parent, name),
+        None => format!("{}: {}", dir, path),
+    }
+}
+
+// Print the one-line summary requested with --brief
+fn print_brief(cli: &Cli, before: &Source, after: &Source, differs: bool) {
+    if cli.brief && differs {
+        println!("Files {} and {} differ", before.name(), after.name());
+    }
+}
+
+// Where one side of the comparison is read from
+enum Source {
+    Stdin,
+    File(String),
+    Inline(&'static str, String),
+    // A file at a git revision, by its path from the top of the worktree;
+    // `name` is the `rev:path` shown to the user
+    Blob { rev: String, path: String, name: String },
+    // A file that does not exist on this side
+    Absent,
+}
+
+impl Source {
+    fn from_arg(arg: &str) -> Source {
+        if arg == "-" {
+            Source::Stdin
+        } else {
+            Source::File(arg.to_string())
+        }
+    }
+    
+    fn read(&self) -> io::Result<Vec<u8>> {
+        match self {
+            Source::Stdin => {
+                let mut data = Vec::new();
+                io::stdin().lock().read_to_end(&mut data)?;
+                Ok(data)
+            },
+            // Also works for named pipes such as /dev/fd/N from process substitution
+            Source::File(path) => fs::read(path),
+            Source::Inline(_, text) => Ok(text.clone().into_bytes()),
+            Source::Blob { rev, path, .. } => {
+                git(&["cat-file", "blob", &format!("{}:{}", rev, path)]).map_err(io::Error::other)
+            },
+            Source::Absent => Ok(Vec::new()),
+        }
     }
     
+    fn reader(&self) -> io::Result<Box<dyn BufRead>> {
+        match self {
+            Source::Stdin => Ok(Box::new(BufReader::new(io::stdin()))),
+            Source::File(path) => Ok(Box::new(BufReader::new(File::open(path)?))),
+            Source::Inline(_, text) => Ok(Box::new(io::Cursor::new(text.clone().into_bytes()))),
+            Source::Blob { .. } | Source::Absent => Ok(Box::new(io::Cursor::new(self.read()?))),
+        }
+    }
+    
+    fn path(&self) -> Option<&str> {
+        match self {
+            Source::File(path) | Source::Blob { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+    
+    fn is_dir(&self) -> bool {
+        self.path().is_some_and(|path| Path::new(path).is_dir())
+    }
+    
+    // Short name of the input, as used by diff(1)
+    fn name(&self) -> &str {
+        match self {
+            Source::Stdin => "-",
+            Source::File(path) => path,
+            Source::Inline(flag, _) => flag,
+            Source::Blob { name, .. } => name,
+            Source::Absent => "/dev/null",
+        }
+    }
+    
+    // Whether the file name has a JSON Lines / NDJSON extension
+    fn is_ndjson(&self) -> bool {
+        self.path()
+            .map(|path| path.to_ascii_lowercase())
+            .is_some_and(|path| path.ends_with(".ndjson") || path.ends_with(".jsonl"))
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Stdin => write!(f, "standard input"),
+            Source::File(path) => write!(f, "file {}", path),
+            Source::Inline(flag, _) => write!(f, "--{}", flag),
+            Source::Blob { name, .. } => write!(f, "{}", name),
+            Source::Absent => write!(f, "/dev/null"),
+        }
+    }
+}
+
+// The arguments git passes to an external diff command: path, old-file,
+// old-hex, old-mode, new-file, new-hex, new-mode and, for renames, the new path
+// and the rename information
+fn external_diff_args(files: &[String]) -> Option<&[String]> {
+    let is_mode = |arg: &str| arg == "." || (arg.len() == 6 && arg.bytes().all(|b| (b'0'..=b'7').contains(&b)));
+    match files.len() {
+        7 | 9 if is_mode(&files[3]) && is_mode(&files[6]) => Some(files),
+        _ => None,
+    }
+}
+
+// Compare one file as invoked by git through GIT_EXTERNAL_DIFF or a
+// diff.<driver>.command
+fn external_diff(cli: &Cli, args: &[String], options: &DiffOptions) -> Result<bool, Box<dyn Error>> {
+    let side = |file: &str, mode: &str| if mode == "." { Source::Absent } else { Source::File(file.to_string()) };
+    let before = side(&args[1], &args[3]);
+    let after = side(&args[4], &args[6]);
+    let new_path = args.get(7).unwrap_or(&args[0]);
+    
+    if !cli.quiet && !cli.brief {
+        print_file_header(&args[0], new_path, &before, &after);
+    }
+    compare_sources(cli, &before, &after, options)?;
+    
+    // git stops at the first external diff that exits with a non-zero status
+    Ok(false)
+}
+
+// Compare the files that changed between two revisions, or between a revision
+// and the working tree
+fn compare_revisions(cli: &Cli, rev1: &str, rev2: Option<&str>, paths: &[String],
+                     options: &DiffOptions) -> Result<bool, Box<dyn Error>> {
+    // Paths are listed relative to the top of the worktree, also for pathspecs
+    // outside the current directory
+    let mut args = vec!["diff", "--name-status", "--no-renames", "-z", rev1];
+    args.extend(rev2);
+    args.push("--");
+    args.extend(paths.iter().map(String::as_str));
+    let output = git(&args)?;
+    
+    // With -z, the output is a sequence of NUL-terminated status and path fields
+    let output = String::from_utf8_lossy(&output);
+    let fields: Vec<&str> = output.split('\0').filter(|field| !field.is_empty()).collect();
+    let top = String::from_utf8_lossy(&git(&["rev-parse", "--show-cdup"])?).trim_end().to_string();
+    
+    let mut differs = false;
+    for entry in fields.chunks(2) {
+        let [status, path] = entry else { continue };
+        if paths.is_empty() && InputFormat::from_path(path).is_none() && !Source::File(path.to_string()).is_ndjson() {
+            continue;
+        }
+        
+        let at = |rev: &str| Source::Blob { rev: rev.to_string(), path: path.to_string(), name: format!("{}:{}", rev, path) };
+        let before = if status.starts_with('A') { Source::Absent } else { at(rev1) };
+        let after = match rev2 {
+            _ if status.starts_with('D') => Source::Absent,
+            Some(rev2) => at(rev2),
+            None => Source::File(format!("{}{}", top, path)),
+        };
+        
+        if !cli.quiet && !cli.brief {
+            print_file_header(path, path, &before, &after);
+        }
+        differs |= compare_sources(cli, &before, &after, options)?;
+    }
+    
+    Ok(differs)
+}
+
+// Print the `---` / `+++` header of one file compared in a git mode
+fn print_file_header(old_path: &str, new_path: &str, before: &Source, after: &Source) {
+    let label = |prefix: &str, path: &str, source: &Source| match source {
+        Source::Absent => "/dev/null".to_string(),
+        _ => format!("{}/{}", prefix, path),
+    };
+    println!("--- {}", label("a", old_path, before));
+    println!("+++ {}", label("b", new_path, after));
+}
+
+// Print a document as pretty-printed JSON with sorted keys, one record per
+// line for NDJSON, so that git can diff the text of any supported format
+fn textconv(cli: &Cli, source: &Source) -> Result<bool, Box<dyn Error>> {
+    let data = source.read()
+        .map_err(|e| format!("Failed to read {}: {}", source, e))?;
+    
+    if cli.ndjson || source.is_ndjson() {
+        let records = parse_ndjson(&String::from_utf8_lossy(&data))
+            .map_err(|e| format!("Failed to parse NDJSON from {}: {}", source, e))?;
+        for record in records {
+            println!("{}", sort_keys(record.value));
+        }
+    } else if let Some(value) = parse_source(cli, source, &data)? {
+        println!("{}", serde_json::to_string_pretty(&sort_keys(value))?);
+    }
+    
+    Ok(false)
+}
+
+// Recursively order object keys alphabetically
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(entries.into_iter().map(|(key, item)| (key, sort_keys(item))).collect())
+        },
+        Value::Array(items) => Value::Array(items.into_iter().map(sort_keys).collect()),
+        other => other,
+    }
+}
+
+// Run git with the given arguments, returning its standard output
+fn git(args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = Process::new("git")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(output.stdout)
+}
+
+// Resolve the original and modified inputs from the command line, exiting with
+// a usage error if they are missing or ambiguous
+fn input_sources(cli: &Cli) -> (Source, Source) {
+    let mut files = cli.files.iter();
+    
+    let before = match &cli.before_string {
+        Some(text) => Some(Source::Inline("before-string", text.clone())),
+        None => files.next().map(|arg| Source::from_arg(arg)),
+    };
+    let after = match &cli.after_string {
+        Some(text) => Some(Source::Inline("after-string", text.clone())),
+        None => files.next().map(|arg| Source::from_arg(arg)),
+    };
+    
+    let mut command = Cli::command();
+    if files.next().is_some() {
+        command.error(ErrorKind::TooManyValues, "too many input files").exit();
+    }
+    match (before, after) {
+        (Some(Source::Stdin), Some(Source::Stdin)) => {
+            command.error(ErrorKind::ArgumentConflict, "only one input can be read from standard input").exit()
+        },
+        (Some(before), Some(after)) => (before, after),
+        _ => command.error(ErrorKind::MissingRequiredArgument, "two inputs are required to compare").exit(),
+    }
+}
+
+// Format of an input: the forced one, or the one detected from its path or
+// its contents
+fn input_format(source: &Source, data: &[u8], forced: Option<InputFormat>, lenient: bool) -> InputFormat {
+    let format = forced
+        .or_else(|| source.path().and_then(InputFormat::from_path))
+        .or_else(|| InputFormat::detect(data))
+        .unwrap_or(InputFormat::Json);
+    
+    if lenient && format == InputFormat::Json {
+        InputFormat::Json5
+    } else {
+        format
+    }
+}
+
+// Add the settings of a JSON configuration file to the options, after those
+// given on the command line:
+//
+//     {"arrays": {"/tags": "unordered", "/users/*/roles": "multiset"},
+//      "normalize": {"/**": "line-endings", "/users/*/email": ["case", "trim"]},
+//      "coerce_types": ["/legacy/**"],
+//      "missing_as_null": true, "missing_as_empty": true, "report_cosmetic": true}
+fn apply_config(path: &str, options: &mut DiffOptions) -> Result<(), String> {
+    let invalid = |message: String| format!("Invalid config file {}: {}", path, message);
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read config file {}: {}", path, e))?;
+    let config: Value = serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+    let Value::Object(config) = config else {
+        return Err(invalid("expected a JSON object".to_string()));
+    };
+
+    for (key, value) in &config {
+        match (key.as_str(), value) {
+            ("arrays", Value::Object(arrays)) => {
+                for (pattern, semantics) in arrays {
+                    let pattern = PathPattern::new(pattern).map_err(invalid)?;
+                    let semantics = semantics.as_str()
+                        .ok_or_else(|| format!("expected a string for array {}", pattern))
+                        .and_then(str::parse)
+                        .map_err(invalid)?;
+                    options.array_semantics.push((pattern, semantics));
+                }
+            },
+            ("arrays", _) => return Err(invalid("'arrays' must map path patterns to semantics".to_string())),
+            ("normalize", Value::Object(normalize)) => {
+                for (pattern, names) in normalize {
+                    let pattern = PathPattern::new(pattern).map_err(invalid)?;
+                    let names = match names {
+                        Value::String(name) => vec![name.as_str()],
+                        Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+                        _ => Vec::new(),
+                    };
+                    if names.is_empty() {
+                        return Err(invalid(format!("expected normalization names for strings {}", pattern)));
+                    }
+                    for name in names {
+                        options.normalizations.push((pattern.clone(), name.parse().map_err(invalid)?));
+                    }
+                }
+            },
+            ("normalize", _) => return Err(invalid("'normalize' must map path patterns to normalizations".to_string())),
+            ("coerce_types", Value::Array(patterns)) => {
+                for pattern in patterns {
+                    let pattern = pattern.as_str().ok_or_else(|| invalid("expected path patterns in 'coerce_types'".to_string()))?;
+                    options.coerce_types.push(PathPattern::new(pattern).map_err(invalid)?);
+                }
+            },
+            ("coerce_types", _) => return Err(invalid("'coerce_types' must be a list of path patterns".to_string())),
+            ("missing_as_null", Value::Bool(enabled)) => options.missing_as_null |= enabled,
+            ("missing_as_empty", Value::Bool(enabled)) => options.missing_as_empty |= enabled,
+            ("report_cosmetic", Value::Bool(enabled)) => options.report_cosmetic |= enabled,
+            ("missing_as_null" | "missing_as_empty" | "report_cosmetic", _) => {
+                return Err(invalid(format!("'{}' must be true or false", key)));
+            },
+            _ => return Err(invalid(format!("unknown setting '{}'", key))),
+        }
+    }
     Ok(())
 }
+
+// Duration given as a sequence of numbers with units d, h, m, s or ms, such
+// as `1h30m` or `2.5s`
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration '{}', expected e.g. 500ms, 30s or 1h30m", s);
+    let mut total = 0.0;
+    let mut rest = s.trim();
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+        let unit_len = rest[number_len..].find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len() - number_len);
+        let number: f64 = rest[..number_len].parse().map_err(|_| invalid())?;
+        let seconds = match &rest[number_len..number_len + unit_len] {
+            "d" => 86_400.0,
+            "h" => 3_600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 0.001,
+            _ => return Err(invalid()),
+        };
+        total += number * seconds;
+        rest = &rest[number_len + unit_len..];
+    }
+    if total.is_nan() || total < 0.0 {
+        return Err(invalid());
+    }
+    Duration::try_from_secs_f64(total).map_err(|_| invalid())
+}
+
+// Size of the streaming window, which must hold at least one element
+fn parse_window(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(0) => Err("the window must hold at least 1 element".to_string()),
+        Ok(window) => Ok(window),
+        Err(e) => Err(format!("invalid window size '{}': {}", s, e)),
+    }
+}
+
+// Normalizations of `--normalize`, such as `case,trim` for all strings or
+// `/users/*/email=case` for the strings at matching paths
+fn parse_normalize(s: &str) -> Result<(PathPattern, Vec<Normalization>), String> {
+    let (pattern, names) = match s.rsplit_once('=') {
+        Some((pattern, names)) => (PathPattern::new(pattern)?, names),
+        None => (PathPattern::any(), s),
+    };
+    let normalizations = names.split(',').map(str::parse).collect::<Result<Vec<_>, _>>()?;
+    Ok((pattern, normalizations))
+}
+
+// Raw JSON representation of NDJSON record changes
+fn changes_to_json(changes: &[RecordChange]) -> Value {
+    let entries = changes.iter()
+        .map(|change| match change {
+            RecordChange::Added { line, value } => json!({ "change": "added", "line": line, "record": value }),
+            RecordChange::Removed { line, value } => json!({ "change": "removed", "line": line, "record": value }),
+            RecordChange::Modified { before_line, after_line, diff } => json!({
+                "change": "modified",
+                "before_line": before_line,
+                "after_line": after_line,
+                "diff": diff,
+            }),
+        })
+        .collect();
+    Value::Array(entries)
+}
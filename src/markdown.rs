@@ -0,0 +1,184 @@
+//! Markdown report of the differences between two JSON structures
+//!
+//! The report is meant to be pasted into pull request comments and wiki pages:
+//! a table of change counts, a table of changed paths with their old and new
+//! values, and the full diff tree in a collapsible `<details>` block, in which
+//! multiline strings are shown as a diff of their lines. Every
+//! part is truncated to the limits in [`MarkdownOptions`] so that very large
+//! diffs stay within the size accepted by those tools.
+
+use serde_json::Value;
+
+use crate::changes::{collect_changes, Change, ChangeKind};
+use crate::core::{compare_json_with_options, format_diff_with_options};
+use crate::options::{DiffOptions, FormatOptions};
+use crate::stats::DiffStats;
+
+// Long and multiline strings in the diff tree show only their changed parts
+const INLINE_DIFF_MIN_LEN: usize = 32;
+const MULTILINE_CONTEXT: usize = 3;
+
+/// Size limits of a Markdown report
+#[derive(Debug, Clone)]
+pub struct MarkdownOptions {
+    /// Maximum number of rows in the table of changed paths
+    pub max_rows: usize,
+    /// Maximum number of characters of a value shown in the table
+    pub max_value_len: usize,
+    /// Maximum number of lines of the diff tree in the `<details>` block
+    pub max_tree_lines: usize,
+}
+
+impl Default for MarkdownOptions {
+    fn default() -> Self {
+        MarkdownOptions {
+            max_rows: 100,
+            max_value_len: 80,
+            max_tree_lines: 500,
+        }
+    }
+}
+
+/// Render the differences between two JSON structures as a Markdown report
+///
+/// # Arguments
+/// * `before` - The original JSON structure
+/// * `after` - The modified JSON structure
+/// * `options` - Comparison options
+/// * `limits` - Size limits of the report
+pub fn format_markdown_report(before: &Value, after: &Value, options: &DiffOptions, limits: &MarkdownOptions) -> String {
+    let changes = collect_changes(before, after, options);
+    if changes.is_empty() {
+        return "No differences found.\n".to_string();
+    }
+
+    let stats = DiffStats::from_changes(&changes);
+    let mut result = String::new();
+
+    // Summary table, with a cosmetic column only when there are cosmetic changes
+    let counts = &stats.total;
+    if counts.cosmetic > 0 {
+        result.push_str("| Added | Removed | Modified | Moved | Cosmetic | Total |\n");
+        result.push_str("|------:|--------:|---------:|------:|---------:|------:|\n");
+        result.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} |\n\n",
+            counts.added, counts.removed, counts.modified, counts.moved, counts.cosmetic, counts.total(),
+        ));
+    } else {
+        result.push_str("| Added | Removed | Modified | Moved | Total |\n");
+        result.push_str("|------:|--------:|---------:|------:|------:|\n");
+        result.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n\n",
+            counts.added, counts.removed, counts.modified, counts.moved, counts.total(),
+        ));
+    }
+
+    // Changed paths
+    result.push_str("| Path | Change | Before | After |\n");
+    result.push_str("|------|--------|--------|-------|\n");
+    for change in changes.iter().take(limits.max_rows) {
+        result.push_str(&table_row(change, limits.max_value_len));
+    }
+    if changes.len() > limits.max_rows {
+        result.push_str(&format!("\n_… and {} more changes_\n", changes.len() - limits.max_rows));
+    }
+
+    // Full tree
+    let format_options = FormatOptions {
+        color: false,
+        inline_diff_min_len: Some(INLINE_DIFF_MIN_LEN),
+        multiline_context: Some(MULTILINE_CONTEXT),
+    };
+    let tree = format_diff_with_options(&compare_json_with_options(before, after, options), 0, &format_options);
+    let lines: Vec<&str> = tree.lines().collect();
+    let mut shown = lines.iter().take(limits.max_tree_lines).copied().collect::<Vec<_>>().join("\n");
+    if lines.len() > limits.max_tree_lines {
+        shown.push_str(&format!("\n… {} more lines", lines.len() - limits.max_tree_lines));
+    }
+    let fence = if shown.contains("```") { "~~~~" } else { "```" };
+
+    result.push_str("\n<details>\n<summary>Full diff</summary>\n\n");
+    result.push_str(&format!("{}\n{}\n{}\n", fence, shown, fence));
+    result.push_str("\n</details>\n");
+
+    result
+}
+
+fn table_row(change: &Change, max_len: usize) -> String {
+    let cell = |value: &Option<Value>| match value {
+        Some(value) => code(&truncate(&value.to_string(), max_len)),
+        None => String::new(),
+    };
+    let kind = match &change.kind {
+        ChangeKind::Added => "added".to_string(),
+        ChangeKind::Removed => "removed".to_string(),
+        ChangeKind::Modified => "modified".to_string(),
+        ChangeKind::Moved { from } => format!("moved from {}", code(from)),
+        ChangeKind::Cosmetic => "cosmetic".to_string(),
+    };
+    let path = if change.path.is_empty() { "(root)".to_string() } else { code(&change.path) };
+    let (before, after) = match change.kind {
+        // Changes inside a moved element have rows of their own
+        ChangeKind::Moved { .. } => (String::new(), String::new()),
+        _ => (cell(&change.before), cell(&change.after)),
+    };
+
+    format!("| {} | {} | {} | {} |\n", path, kind, before, after)
+}
+
+// Inline code span that is safe inside a table cell
+fn code(text: &str) -> String {
+    let text = text.replace('|', "\\|");
+    if text.contains('`') {
+        format!("`` {} ``", text)
+    } else {
+        format!("`{}`", text)
+    }
+}
+
+fn truncate(text: &str, max_len: usize) -> String {
+    if text.chars().count() <= max_len {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max_len.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::changes::has_differences;
+    use serde_json::json;
+
+    fn report(before: &Value, after: &Value, options: &DiffOptions) -> String {
+        format_markdown_report(before, after, options, &MarkdownOptions::default())
+    }
+
+    #[test]
+    fn moves_are_reported_for_differing_documents() {
+        let (before, after) = (json!([1, 2]), json!([2, 1]));
+        let report = report(&before, &after, &DiffOptions::default());
+        assert!(report.starts_with("| Added | Removed | Modified | Moved | Total |"), "{}", report);
+        assert!(report.contains("| 0 | 0 | 0 | 1 | 1 |"), "{}", report);
+        assert!(report.contains("| `/1` | moved from `/0` |  |  |"), "{}", report);
+        assert!(has_differences(&before, &after, &DiffOptions::default()));
+    }
+
+    #[test]
+    fn equal_documents_have_no_tables() {
+        let (before, after) = (json!({"a": [1, 2], "b": null}), json!({"a": [1, 2]}));
+        let options = DiffOptions { missing_as_null: true, ..DiffOptions::default() };
+        assert_eq!(report(&before, &after, &options), "No differences found.\n");
+        assert!(!has_differences(&before, &after, &options));
+    }
+
+    #[test]
+    fn cosmetic_column_only_with_cosmetic_changes() {
+        let (before, after) = (json!({"a": null, "b": 1}), json!({"b": 2}));
+        let options = DiffOptions { missing_as_null: true, report_cosmetic: true, ..DiffOptions::default() };
+        let report = report(&before, &after, &options);
+        assert!(report.contains("| 0 | 0 | 1 | 0 | 1 | 2 |"), "{}", report);
+        assert!(report.contains("| `/a` | cosmetic | `null` |  |"), "{}", report);
+    }
+}
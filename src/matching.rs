@@ -0,0 +1,354 @@
+//! Matching of array elements between the original and modified arrays
+//!
+//! Elements are paired in several passes so that large arrays do not need a
+//! full scan of `after` for every element of `before`:
+//!
+//! 1. Exact matches on content, looked up by a structural hash
+//! 2. Exact matches on the object identifier (see `find_identifier_key_value`)
+//! 3. Fuzzy identifier matching by Levenshtein similarity, only for the
+//!    elements left unmatched by the previous passes and skipped when that
+//!    would exceed [`DiffOptions::max_fuzzy_comparisons`]
+//!
+//! Arrays longer than [`DiffOptions::max_match_len`] and arrays with
+//! [`ArraySemantics::Ordered`] skip matching entirely and are paired by
+//! position. Arrays compared as sets or multisets are not paired at all; only
+//! the members missing on either side are looked up.
+//!
+//! With the `parallel` feature, hashing and the fuzzy candidate scan run on the
+//! rayon thread pool; the resulting pairing is the same as the sequential one.
+
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+#[cfg(feature = "parallel")]
+use crate::core::PARALLEL_MIN_LEN;
+use crate::core::{get_identifier, string_similarity};
+use crate::options::{ArraySemantics, DiffOptions};
+
+/// Pair elements of `before` with elements of `after`, the arrays found at the
+/// JSON Pointer `path`
+///
+/// Returns a vector with one entry per element of `before` holding the index of
+/// the matched element in `after`, or `None` if the element was deleted.
+pub(crate) fn match_arrays(before: &[Value], after: &[Value], path: &str, options: &DiffOptions) -> Vec<Option<usize>> {
+    if before.len() > options.max_match_len || after.len() > options.max_match_len
+        || array_semantics(path, options) == Some(ArraySemantics::Ordered) {
+        return (0..before.len())
+            .map(|i| if i < after.len() { Some(i) } else { None })
+            .collect();
+    }
+
+    let mut matches: Vec<Option<usize>> = vec![None; before.len()];
+    let mut matched_after = vec![false; after.len()];
+
+    // Pass 1: identical elements
+    let mut by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, hash) in hash_all(after).into_iter().enumerate() {
+        by_hash.entry(hash).or_default().push(i);
+    }
+
+    for (i, (item, hash)) in before.iter().zip(hash_all(before)).enumerate() {
+        if let Some(candidates) = by_hash.get(&hash) {
+            if let Some(&j) = candidates.iter().find(|&&j| !matched_after[j] && &after[j] == item) {
+                matches[i] = Some(j);
+                matched_after[j] = true;
+            }
+        }
+    }
+
+    // Pass 2: objects with the same identifier value
+    let mut by_identifier: HashMap<String, Vec<usize>> = HashMap::new();
+    for (j, item) in after.iter().enumerate() {
+        if matched_after[j] {
+            continue;
+        }
+        if let Some(id) = identity_key(item) {
+            by_identifier.entry(id).or_default().push(j);
+        }
+    }
+
+    for (i, item) in before.iter().enumerate() {
+        if matches[i].is_some() {
+            continue;
+        }
+        let candidates = match identity_key(item).and_then(|id| by_identifier.get(&id)) {
+            Some(candidates) => candidates,
+            None => continue,
+        };
+        if let Some(&j) = candidates.iter().find(|&&j| !matched_after[j]) {
+            matches[i] = Some(j);
+            matched_after[j] = true;
+        }
+    }
+
+    // Pass 3: objects with similar string identifiers
+    let remaining_after: Vec<(usize, String)> = after.iter()
+        .enumerate()
+        .filter(|(j, _)| !matched_after[*j])
+        .filter_map(|(j, item)| string_identifier(item).map(|id| (j, id)))
+        .collect();
+
+    let remaining_before = before.iter()
+        .enumerate()
+        .filter(|(i, item)| matches[*i].is_none() && string_identifier(item).is_some())
+        .count();
+
+    if remaining_after.is_empty()
+        || remaining_before.saturating_mul(remaining_after.len()) > options.max_fuzzy_comparisons {
+        return matches;
+    }
+
+    for (i, item) in before.iter().enumerate() {
+        if matches[i].is_some() {
+            continue;
+        }
+        let before_id = match string_identifier(item) {
+            Some(id) => id,
+            None => continue,
+        };
+
+        if let Some(j) = best_fuzzy_match(&before_id, &remaining_after, &matched_after, options) {
+            matches[i] = Some(j);
+            matched_after[j] = true;
+        }
+    }
+
+    matches
+}
+
+/// Semantics of the array at `path` from [`DiffOptions::array_semantics`]
+pub(crate) fn array_semantics(path: &str, options: &DiffOptions) -> Option<ArraySemantics> {
+    options.array_semantics.iter()
+        .find(|(pattern, _)| pattern.matches(path))
+        .map(|(_, semantics)| *semantics)
+}
+
+/// Members of two arrays compared as sets or multisets that the other array
+/// lacks: the indices of the removed elements of `before` and of the added
+/// elements of `after`
+///
+/// As sets, each missing value is reported once, at its first occurrence; as
+/// multisets, every occurrence without an identical counterpart is reported.
+pub(crate) fn diff_members(before: &[Value], after: &[Value], semantics: ArraySemantics) -> (Vec<usize>, Vec<usize>) {
+    let before_hashes = hash_all(before);
+    let after_hashes = hash_all(after);
+
+    if semantics == ArraySemantics::Unordered {
+        return (
+            missing_members(before, &before_hashes, after, &after_hashes),
+            missing_members(after, &after_hashes, before, &before_hashes),
+        );
+    }
+
+    // Multiset: pair identical occurrences one to one
+    let mut by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (j, hash) in after_hashes.iter().enumerate() {
+        by_hash.entry(*hash).or_default().push(j);
+    }
+    let mut matched_after = vec![false; after.len()];
+    let mut removed = Vec::new();
+    for (i, (item, hash)) in before.iter().zip(&before_hashes).enumerate() {
+        let counterpart = by_hash.get(hash)
+            .and_then(|candidates| candidates.iter().find(|&&j| !matched_after[j] && &after[j] == item));
+        match counterpart {
+            Some(&j) => matched_after[j] = true,
+            None => removed.push(i),
+        }
+    }
+    let added = (0..after.len()).filter(|&j| !matched_after[j]).collect();
+    (removed, added)
+}
+
+// First occurrences of the values of `items` that do not occur in `other`
+fn missing_members(items: &[Value], hashes: &[u64], other: &[Value], other_hashes: &[u64]) -> Vec<usize> {
+    let mut other_by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (j, hash) in other_hashes.iter().enumerate() {
+        other_by_hash.entry(*hash).or_default().push(j);
+    }
+
+    let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+    let mut missing = Vec::new();
+    for (i, (item, hash)) in items.iter().zip(hashes).enumerate() {
+        let present = other_by_hash.get(hash).is_some_and(|js| js.iter().any(|&j| &other[j] == item));
+        let repeated = seen.get(hash).is_some_and(|ks| ks.iter().any(|&k| &items[k] == item));
+        if !present && !repeated {
+            missing.push(i);
+        }
+        seen.entry(*hash).or_default().push(i);
+    }
+    missing
+}
+
+// Find the unmatched candidate whose identifier is most similar to `before_id`,
+// preferring the earliest candidate on ties
+fn best_fuzzy_match(before_id: &str, candidates: &[(usize, String)], matched_after: &[bool],
+                    options: &DiffOptions) -> Option<usize> {
+    let similarity_of = |(j, after_id): &(usize, String)| {
+        if matched_after[*j] {
+            return None;
+        }
+        let similarity = string_similarity(before_id, after_id);
+        if similarity > options.similarity_threshold {
+            Some((*j, similarity))
+        } else {
+            None
+        }
+    };
+    let better = |best: Option<(usize, f64)>, next: Option<(usize, f64)>| match (best, next) {
+        (Some((bj, bs)), Some((nj, ns))) => {
+            if ns > bs || (ns == bs && nj < bj) { next } else { best }
+        },
+        (None, next) => next,
+        (best, None) => best,
+    };
+
+    #[cfg(feature = "parallel")]
+    {
+        if candidates.len() >= PARALLEL_MIN_LEN {
+            return candidates.par_iter()
+                .map(similarity_of)
+                .reduce(|| None, better)
+                .map(|(j, _)| j);
+        }
+    }
+
+    candidates.iter()
+        .map(similarity_of)
+        .fold(None, better)
+        .map(|(j, _)| j)
+}
+
+// Structural hashes of all elements of an array
+fn hash_all(items: &[Value]) -> Vec<u64> {
+    #[cfg(feature = "parallel")]
+    {
+        if items.len() >= PARALLEL_MIN_LEN {
+            return items.par_iter().map(value_hash).collect();
+        }
+    }
+
+    items.iter().map(value_hash).collect()
+}
+
+// Identifier of an object element as a lookup key, if it has a scalar identifier
+pub(crate) fn identity_key(item: &Value) -> Option<String> {
+    match item {
+        Value::Object(obj) => match get_identifier(obj) {
+            Some(id @ Value::String(_)) | Some(id @ Value::Number(_)) => Some(id.to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// String identifier of an object element, used for fuzzy matching
+fn string_identifier(item: &Value) -> Option<String> {
+    match item {
+        Value::Object(obj) => match get_identifier(obj) {
+            Some(Value::String(s)) => Some(s),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Structural hash of a JSON value, consistent with `Value` equality
+pub(crate) fn value_hash(value: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hash_value(value, &mut hasher);
+    hasher.finish()
+}
+
+fn hash_value<H: Hasher>(value: &Value, state: &mut H) {
+    match value {
+        Value::Null => 0u8.hash(state),
+        Value::Bool(b) => {
+            1u8.hash(state);
+            b.hash(state);
+        },
+        Value::Number(n) => {
+            2u8.hash(state);
+            n.to_string().hash(state);
+        },
+        Value::String(s) => {
+            3u8.hash(state);
+            s.hash(state);
+        },
+        Value::Array(arr) => {
+            4u8.hash(state);
+            arr.len().hash(state);
+            for item in arr {
+                hash_value(item, state);
+            }
+        },
+        Value::Object(obj) => {
+            5u8.hash(state);
+            obj.len().hash(state);
+            // Sorted, as maps may keep keys in insertion order (`preserve_order`)
+            let mut entries: Vec<(&String, &Value)> = obj.iter().collect();
+            entries.sort_unstable_by_key(|(key, _)| *key);
+            for (key, item) in entries {
+                key.hash(state);
+                hash_value(item, state);
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern::PathPattern;
+    use serde_json::json;
+
+    fn pairs(before: Value, after: Value, options: &DiffOptions) -> Vec<Option<usize>> {
+        match (before, after) {
+            (Value::Array(before), Value::Array(after)) => match_arrays(&before, &after, "", options),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn identical_elements_are_matched_wherever_they_moved() {
+        let options = DiffOptions::default();
+        assert_eq!(pairs(json!([1, 2, 3]), json!([3, 1, 2]), &options), vec![Some(1), Some(2), Some(0)]);
+        assert_eq!(pairs(json!([1, 1]), json!([1]), &options), vec![Some(0), None]);
+        assert_eq!(pairs(json!([{"a": 1, "b": 2}]), json!([true, {"b": 2, "a": 1}]), &options), vec![Some(1)]);
+    }
+
+    #[test]
+    fn objects_are_matched_by_identifier() {
+        let before = json!([{"name": "a", "v": 1}, {"name": "b", "v": 1}, {"id": 7, "v": 1}]);
+        let after = json!([{"id": 7, "v": 2}, {"name": "b", "v": 2}, {"name": "a", "v": 2}]);
+        assert_eq!(pairs(before, after, &DiffOptions::default()), vec![Some(2), Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn similar_string_identifiers_are_matched() {
+        let before = json!([{"name": "alpha-service", "v": 1}, {"name": "other", "v": 1}]);
+        let after = json!([{"name": "beta", "v": 2}, {"name": "alpha-servicex", "v": 2}]);
+        assert_eq!(pairs(before.clone(), after.clone(), &DiffOptions::default()), vec![Some(1), None]);
+
+        // Skipped when the scan would take more comparisons than allowed
+        let options = DiffOptions { max_fuzzy_comparisons: 3, ..DiffOptions::default() };
+        assert_eq!(pairs(before, after, &options), vec![None, None]);
+    }
+
+    #[test]
+    fn long_and_ordered_arrays_are_paired_by_position() {
+        let options = DiffOptions { max_match_len: 2, ..DiffOptions::default() };
+        assert_eq!(pairs(json!([1, 2]), json!([2, 1]), &options), vec![Some(1), Some(0)]);
+        assert_eq!(pairs(json!([1, 2, 3]), json!([3, 1]), &options), vec![Some(0), Some(1), None]);
+
+        let options = DiffOptions {
+            array_semantics: vec![(PathPattern::new("").unwrap(), ArraySemantics::Ordered)],
+            ..DiffOptions::default()
+        };
+        assert_eq!(pairs(json!([1, 2]), json!([2, 1]), &options), vec![Some(0), Some(1)]);
+    }
+}
@@ -0,0 +1,196 @@
+//! Record-level comparison of JSON Lines / NDJSON documents
+//!
+//! Each non-empty line of an NDJSON document is one record. Records are paired
+//! either by the value of a key field or, without a key, by the same matching
+//! used for array elements, and only added, removed and modified records are
+//! reported together with their line numbers.
+
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::changes::has_differences;
+use crate::core::{compare_json_with_options, format_diff_to_string};
+use crate::matching::match_arrays;
+use crate::options::DiffOptions;
+
+/// A record read from an NDJSON document
+#[derive(Debug, Clone, PartialEq)]
+pub struct NdjsonRecord {
+    /// 1-based line number of the record
+    pub line: usize,
+    /// Parsed record
+    pub value: Value,
+}
+
+/// A difference between the records of two NDJSON documents
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordChange {
+    /// Record only present in the modified document
+    Added { line: usize, value: Value },
+    /// Record only present in the original document
+    Removed { line: usize, value: Value },
+    /// Record present in both documents with different contents; `diff` is the
+    /// result of comparing the two records
+    Modified { before_line: usize, after_line: usize, diff: Value },
+}
+
+/// Parse an NDJSON document into records, skipping blank lines
+///
+/// # Returns
+/// The records in document order, or an error naming the first invalid line
+pub fn parse_ndjson(text: &str) -> Result<Vec<NdjsonRecord>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .map(|value| NdjsonRecord { line: i + 1, value })
+                .map_err(|e| format!("line {}: {}", i + 1, e))
+        })
+        .collect()
+}
+
+/// Compare two sequences of NDJSON records
+///
+/// # Arguments
+/// * `before` - Records of the original document
+/// * `after` - Records of the modified document
+/// * `key` - Optional field identifying a record: a top-level field name or a
+///   JSON Pointer such as `/meta/id`. Without a key, records are matched like
+///   array elements in [`compare_json`](crate::compare_json)
+/// * `options` - Options used for matching and for comparing matched records
+///
+/// # Returns
+/// Removed and modified records in the order of `before`, followed by added
+/// records in the order of `after`
+pub fn compare_ndjson(before: &[NdjsonRecord], after: &[NdjsonRecord], key: Option<&str>,
+                      options: &DiffOptions) -> Vec<RecordChange> {
+    let matches = match key {
+        Some(key) => match_by_key(before, after, key),
+        None => {
+            let before_values: Vec<Value> = before.iter().map(|r| r.value.clone()).collect();
+            let after_values: Vec<Value> = after.iter().map(|r| r.value.clone()).collect();
+            match_arrays(&before_values, &after_values, "", options)
+        },
+    };
+
+    let mut changes = Vec::new();
+    let mut matched_after = vec![false; after.len()];
+
+    for (record, match_idx) in before.iter().zip(matches) {
+        match match_idx {
+            Some(idx) => {
+                matched_after[idx] = true;
+                let other = &after[idx];
+                if has_differences(&record.value, &other.value, options) {
+                    changes.push(RecordChange::Modified {
+                        before_line: record.line,
+                        after_line: other.line,
+                        diff: compare_json_with_options(&record.value, &other.value, options),
+                    });
+                }
+            },
+            None => changes.push(RecordChange::Removed { line: record.line, value: record.value.clone() }),
+        }
+    }
+
+    for (record, matched) in after.iter().zip(matched_after) {
+        if !matched {
+            changes.push(RecordChange::Added { line: record.line, value: record.value.clone() });
+        }
+    }
+
+    changes
+}
+
+/// Format record changes, one record per entry
+///
+/// Added and removed records are printed on a single line prefixed with `+` or
+/// `-`; modified records are followed by their formatted diff.
+pub fn format_ndjson_diff(changes: &[RecordChange]) -> String {
+    let mut result = String::new();
+
+    for change in changes {
+        match change {
+            RecordChange::Added { line, value } => {
+                result.push_str(&format!("+ line {}: {}\n", line, value));
+            },
+            RecordChange::Removed { line, value } => {
+                result.push_str(&format!("- line {}: {}\n", line, value));
+            },
+            RecordChange::Modified { before_line, after_line, diff } => {
+                result.push_str(&format!("~ line {} => line {}:\n", before_line, after_line));
+                result.push_str(&format!("  {}\n", format_diff_to_string(diff, 2)));
+            },
+        }
+    }
+
+    result
+}
+
+// Pair records whose key field holds the same value
+fn match_by_key(before: &[NdjsonRecord], after: &[NdjsonRecord], key: &str) -> Vec<Option<usize>> {
+    let mut by_key: HashMap<String, Vec<usize>> = HashMap::new();
+    for (j, record) in after.iter().enumerate() {
+        if let Some(id) = record_key(&record.value, key) {
+            by_key.entry(id).or_default().push(j);
+        }
+    }
+
+    let mut matched_after = vec![false; after.len()];
+    before.iter()
+        .map(|record| {
+            let candidates = by_key.get(&record_key(&record.value, key)?)?;
+            let idx = candidates.iter().copied().find(|j| !matched_after[*j])?;
+            matched_after[idx] = true;
+            Some(idx)
+        })
+        .collect()
+}
+
+// Value of the key field of a record as a lookup key
+fn record_key(value: &Value, key: &str) -> Option<String> {
+    let field = if key.starts_with('/') {
+        value.pointer(key)
+    } else {
+        value.get(key)
+    };
+    field.map(|v| v.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn records(text: &str) -> Vec<NdjsonRecord> {
+        parse_ndjson(text).unwrap()
+    }
+
+    #[test]
+    fn records_equal_under_options_are_not_modified() {
+        let before = records("{\"id\":\"a\",\"x\":1}\n{\"id\":\"b\",\"x\":null}\n");
+        let after = records("{\"id\":\"a\",\"x\":1}\n{\"id\":\"b\"}\n");
+
+        let changes = compare_ndjson(&before, &after, Some("id"), &DiffOptions::default());
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(changes[0], RecordChange::Modified { before_line: 2, after_line: 2, .. }));
+
+        let options = DiffOptions { missing_as_null: true, ..DiffOptions::default() };
+        assert!(compare_ndjson(&before, &after, Some("id"), &options).is_empty());
+        assert!(compare_ndjson(&before, &after, None, &options).is_empty());
+    }
+
+    #[test]
+    fn modified_records_hold_their_diff() {
+        let before = records("{\"id\":1,\"x\":\"old\"}");
+        let after = records("\n{\"id\":1,\"x\":\"new\"}");
+
+        let changes = compare_ndjson(&before, &after, Some("id"), &DiffOptions::default());
+        assert_eq!(changes, vec![RecordChange::Modified {
+            before_line: 1,
+            after_line: 2,
+            diff: json!({"id": 1, "x": "old => new"}),
+        }]);
+    }
+}
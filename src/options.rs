@@ -0,0 +1,209 @@
+//! Options controlling how JSON structures are compared and formatted
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::comparator::Comparator;
+use crate::pattern::PathPattern;
+
+/// Settings used by [`compare_json_with_options`](crate::compare_json_with_options)
+///
+/// The defaults reproduce the behavior of [`compare_json`](crate::compare_json).
+#[derive(Debug, Clone)]
+pub struct DiffOptions {
+    /// Add auto-incremental index field to objects in arrays
+    pub add_idx: bool,
+
+    /// Minimum identifier similarity (0.0 - 1.0) for two array objects to be
+    /// treated as the same element during fuzzy matching
+    pub similarity_threshold: f64,
+
+    /// Arrays longer than this are compared position by position instead of
+    /// being matched element by element
+    pub max_match_len: usize,
+
+    /// Upper bound on the number of identifier similarity computations in the
+    /// fuzzy matching pass; when the unmatched elements would need more, they
+    /// are reported as deleted and added instead
+    pub max_fuzzy_comparisons: usize,
+
+    /// Maximum number of unmatched array elements held per side when comparing
+    /// in streaming mode; older elements are reported as deleted or added
+    pub stream_window: usize,
+
+    /// Strings holding a JSON-encoded object or array at paths matching these
+    /// patterns are parsed on both sides and their contents compared; use
+    /// [`PathPattern::any`] to parse embedded documents everywhere
+    pub embedded_json: Vec<PathPattern>,
+
+    /// Strings holding an RFC 3339 date-time on both sides at paths matching
+    /// these patterns are compared as instants, so that the same time written
+    /// with different offsets or precision is equal
+    pub datetime: Vec<PathPattern>,
+
+    /// Largest time between two instants compared as date-times that is still
+    /// treated as equal
+    pub datetime_tolerance: Duration,
+
+    /// Custom comparisons consulted in order before the default comparison;
+    /// the first comparator that applies to a pair of values decides
+    pub comparators: Vec<Comparator>,
+
+    /// How arrays at paths matching each pattern are compared; the first
+    /// matching pattern applies, and other arrays are matched element by
+    /// element
+    pub array_semantics: Vec<(PathPattern, ArraySemantics)>,
+
+    /// Treat an object key that is missing on one side as equal to `null` on
+    /// the other side
+    pub missing_as_null: bool,
+
+    /// Treat an object key that is missing on one side as equal to an empty
+    /// array or object on the other side
+    pub missing_as_empty: bool,
+
+    /// Normalizations applied to both strings at paths matching each pattern
+    /// before they are compared; every matching pattern applies, and changed
+    /// strings are still shown with their original values
+    pub normalizations: Vec<(PathPattern, Normalization)>,
+
+    /// At paths matching these patterns, a string holding a number or a
+    /// boolean is equal to that number or boolean, such as `"42"` and `42`,
+    /// `"1.50"` and `1.5` or `"true"` and `true`, and numbers are equal by
+    /// value, such as `1` and `1.0`
+    pub coerce_types: Vec<PathPattern>,
+
+    /// Report values that are only equal because of the options above as
+    /// cosmetic changes instead of ignoring them; cosmetic changes do not make
+    /// the documents differ
+    pub report_cosmetic: bool,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions {
+            add_idx: false,
+            similarity_threshold: 0.75,
+            max_match_len: 100_000,
+            max_fuzzy_comparisons: 1_000_000,
+            stream_window: 10_000,
+            embedded_json: Vec::new(),
+            datetime: Vec::new(),
+            datetime_tolerance: Duration::ZERO,
+            comparators: Vec::new(),
+            array_semantics: Vec::new(),
+            missing_as_null: false,
+            missing_as_empty: false,
+            normalizations: Vec::new(),
+            coerce_types: Vec::new(),
+            report_cosmetic: false,
+        }
+    }
+}
+
+/// How the elements of an array are compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArraySemantics {
+    /// Elements are compared position by position, so a reordering is a change
+    Ordered,
+    /// The array is a set: order and duplicates are ignored, and only the
+    /// members added or removed are reported
+    Unordered,
+    /// The array is a multiset: order is ignored, each occurrence of a member
+    /// counts, and only the occurrences added or removed are reported
+    Multiset,
+}
+
+impl FromStr for ArraySemantics {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ordered" => Ok(ArraySemantics::Ordered),
+            "unordered" | "set" => Ok(ArraySemantics::Unordered),
+            "multiset" => Ok(ArraySemantics::Multiset),
+            _ => Err(format!("Unknown array semantics '{}', expected ordered, unordered or multiset", s)),
+        }
+    }
+}
+
+impl fmt::Display for ArraySemantics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ArraySemantics::Ordered => "ordered",
+            ArraySemantics::Unordered => "unordered",
+            ArraySemantics::Multiset => "multiset",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Normalization of strings before they are compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// Compare strings case-insensitively
+    Case,
+    /// Ignore leading and trailing whitespace
+    Trim,
+    /// Treat each run of whitespace as a single space
+    Whitespace,
+    /// Unicode canonical composition (NFC), so that composed and decomposed
+    /// accents are equal
+    Nfc,
+    /// Unicode compatibility composition (NFKC), which also folds ligatures,
+    /// full-width forms and the like
+    Nfkc,
+    /// Treat `\r\n` and `\r` line endings as `\n`
+    LineEndings,
+}
+
+impl FromStr for Normalization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "case" => Ok(Normalization::Case),
+            "trim" => Ok(Normalization::Trim),
+            "whitespace" => Ok(Normalization::Whitespace),
+            "nfc" => Ok(Normalization::Nfc),
+            "nfkc" => Ok(Normalization::Nfkc),
+            "line-endings" => Ok(Normalization::LineEndings),
+            _ => Err(format!(
+                "Unknown normalization '{}', expected case, trim, whitespace, nfc, nfkc or line-endings", s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Normalization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Normalization::Case => "case",
+            Normalization::Trim => "trim",
+            Normalization::Whitespace => "whitespace",
+            Normalization::Nfc => "nfc",
+            Normalization::Nfkc => "nfkc",
+            Normalization::LineEndings => "line-endings",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Settings used by [`format_diff_with_options`](crate::format_diff_with_options)
+///
+/// The defaults reproduce the output of [`format_diff_to_string`](crate::format_diff_to_string).
+#[derive(Debug, Clone, Default)]
+pub struct FormatOptions {
+    /// Highlight changes with ANSI colors instead of bracketing them
+    pub color: bool,
+
+    /// Show only the changed words and characters of modified strings at
+    /// least this many characters long; `None` always shows both strings whole
+    pub inline_diff_min_len: Option<usize>,
+
+    /// Show modified multiline strings as a unified diff of their lines with
+    /// this many unchanged lines around each change; `None` shows both strings
+    /// whole
+    pub multiline_context: Option<usize>,
+}
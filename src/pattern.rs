@@ -0,0 +1,136 @@
+//! Patterns selecting the values a comparison option applies to
+//!
+//! A pattern is a JSON Pointer whose segments may contain glob wildcards: `*`,
+//! `?` and `[...]` match within a single segment, so `/users/*/email` selects
+//! the email of every user, and a `**` segment matches any number of segments,
+//! so `/**/payload` selects `payload` keys at any depth. Array elements are
+//! selected by their index in the modified document, or in the original
+//! document for removed elements.
+
+use glob::Pattern;
+use std::fmt;
+use std::str::FromStr;
+
+/// JSON Pointer pattern selecting values by their path
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    /// `**`: any number of segments
+    Any,
+    Glob(Pattern),
+}
+
+impl PathPattern {
+    /// Compile a pattern such as `/users/*/email`
+    ///
+    /// # Returns
+    /// The pattern, or an error if it is neither empty nor starts with `/`, or
+    /// if a segment is not a valid glob
+    pub fn new(pattern: &str) -> Result<PathPattern, String> {
+        if !pattern.is_empty() && !pattern.starts_with('/') {
+            return Err(format!("Invalid path pattern '{}': must be empty or start with '/'", pattern));
+        }
+
+        let segments = pattern.split('/')
+            .skip(1)
+            .map(|segment| match segment {
+                "**" => Ok(Segment::Any),
+                _ => Pattern::new(segment)
+                    .map(Segment::Glob)
+                    .map_err(|e| format!("Invalid path pattern '{}': {}", pattern, e)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(PathPattern { source: pattern.to_string(), segments })
+    }
+
+    /// Pattern selecting every value, `/**`
+    pub fn any() -> PathPattern {
+        PathPattern { source: "/**".to_string(), segments: vec![Segment::Any] }
+    }
+
+    /// Whether the value at a JSON Pointer path is selected
+    pub fn matches(&self, path: &str) -> bool {
+        let path: Vec<&str> = path.split('/').skip(1).collect();
+        matches_segments(&self.segments, &path)
+    }
+}
+
+/// Whether any of the patterns selects the value at `path`
+pub(crate) fn matches_any(patterns: &[PathPattern], path: &str) -> bool {
+    patterns.iter().any(|pattern| pattern.matches(path))
+}
+
+fn matches_segments(pattern: &[Segment], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((Segment::Any, rest)) => (0..=path.len()).any(|skip| matches_segments(rest, &path[skip..])),
+        Some((Segment::Glob(glob), rest)) => match path.split_first() {
+            Some((segment, path_rest)) => glob.matches(segment) && matches_segments(rest, path_rest),
+            None => false,
+        },
+    }
+}
+
+impl FromStr for PathPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PathPattern::new(s)
+    }
+}
+
+impl fmt::Display for PathPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        PathPattern::new(pattern).unwrap().matches(path)
+    }
+
+    #[test]
+    fn wildcards_match_within_a_segment() {
+        assert!(matches("/users/*/email", "/users/0/email"));
+        assert!(matches("/users/*/email", "/users/alice/email"));
+        assert!(!matches("/users/*/email", "/users/0/1/email"));
+        assert!(!matches("/users/*/email", "/users/0"));
+        assert!(matches("/item?/[ab]", "/item1/b"));
+        assert!(!matches("/item?/[ab]", "/item1/c"));
+    }
+
+    #[test]
+    fn double_star_matches_any_number_of_segments() {
+        assert!(matches("/**/payload", "/payload"));
+        assert!(matches("/**/payload", "/a/0/payload"));
+        assert!(!matches("/**/payload", "/a/payload/b"));
+        assert!(matches("/a/**", "/a"));
+        assert!(matches("/a/**", "/a/b/c"));
+        assert!(PathPattern::any().matches(""));
+        assert!(PathPattern::any().matches("/x/y"));
+    }
+
+    #[test]
+    fn empty_pattern_selects_the_root_only() {
+        assert!(matches("", ""));
+        assert!(!matches("", "/a"));
+        assert!(!matches("/a", ""));
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        assert!(PathPattern::new("users/*").is_err());
+        assert!(PathPattern::new("/users/[").is_err());
+        assert_eq!(PathPattern::new("/a/**").unwrap().to_string(), "/a/**");
+    }
+}
@@ -2,25 +2,45 @@
 // Only compiled when the "python-bindings" feature is enabled
 
 use std::fs;
+use std::sync::{Arc, Mutex};
 use pyo3::prelude::*;
-use pyo3::exceptions::PyValueError;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::types::{PyBool, PyString};
 use serde_json::Value;
 
-use crate::core::{compare_json, format_diff_to_string};
+use crate::comparator::{Comparator, Comparison};
+use crate::core::{compare_json_with_options, format_diff_to_string};
+use crate::input::{parse_document, parse_document_bytes, InputFormat};
+use crate::options::DiffOptions;
+use crate::pattern::PathPattern;
+use crate::stats::DiffStats;
+
+/// Custom comparators as `(selector, compare)` pairs; see [`compare_with_comparators`]
+type PyComparators = Option<Vec<(PyObject, PyObject)>>;
 
 /// Compare two JSON strings and return a formatted string showing the differences
+///
+/// With `lenient=True`, comments, trailing commas and JSON5 syntax are accepted.
+/// `comparators` is a list of `(selector, compare)` pairs: the selector is a
+/// path pattern such as `"/users/*/email"` or a callable `(path, before, after)`
+/// returning whether the comparator applies, and `compare(before, after)`
+/// returns `True` for equal values, `False` for different values, or a string
+/// to show as the change.
 #[pyfunction]
-#[pyo3(signature = (before_json, after_json, add_idx=None))]
-pub fn compare_json_strings(before_json: &str, after_json: &str, add_idx: Option<bool>) -> PyResult<String> {
+#[pyo3(signature = (before_json, after_json, add_idx=None, lenient=None, comparators=None))]
+pub fn compare_json_strings(py: Python, before_json: &str, after_json: &str, add_idx: Option<bool>,
+                            lenient: Option<bool>, comparators: PyComparators) -> PyResult<String> {
+    let format = if lenient.unwrap_or(false) { InputFormat::Json5 } else { InputFormat::Json };
+    
     // Parse JSON strings
-    let before = serde_json::from_str(before_json)
+    let before = parse_document(before_json, format)
         .map_err(|e| PyValueError::new_err(format!("Failed to parse 'before' JSON: {}", e)))?;
     
-    let after = serde_json::from_str(after_json)
+    let after = parse_document(after_json, format)
         .map_err(|e| PyValueError::new_err(format!("Failed to parse 'after' JSON: {}", e)))?;
     
     // Compare and format
-    let result = compare_json(&before, &after, add_idx);
+    let result = compare_with_comparators(py, &before, &after, add_idx, comparators)?;
     let formatted = format_diff_to_string(&result, 0);
     
     Ok(formatted)
@@ -29,8 +49,9 @@ pub fn compare_json_strings(before_json: &str, after_json: &str, add_idx: Option
 /// Compare two Python objects that can be converted to JSON and return a formatted string
 /// showing the differences
 #[pyfunction]
-#[pyo3(signature = (before_obj, after_obj, add_idx=None))]
-pub fn compare_json_values(py: Python, before_obj: PyObject, after_obj: PyObject, add_idx: Option<bool>) -> PyResult<String> {
+#[pyo3(signature = (before_obj, after_obj, add_idx=None, comparators=None))]
+pub fn compare_json_values(py: Python, before_obj: PyObject, after_obj: PyObject, add_idx: Option<bool>,
+                           comparators: PyComparators) -> PyResult<String> {
     // Convert Python objects to JSON strings using Python's json module
     let json = PyModule::import(py, "json")?;
     
@@ -41,30 +62,150 @@ pub fn compare_json_values(py: Python, before_obj: PyObject, after_obj: PyObject
         .extract::<String>()?;
     
     // Use the string comparison function
-    compare_json_strings(&before_json, &after_json, add_idx)
+    compare_json_strings(py, &before_json, &after_json, add_idx, None, comparators)
 }
 
 /// Compare two JSON files and return a formatted string showing the differences
+///
+/// `.jsonc` and `.json5` files, and all JSON files with `lenient=True`, may
+/// contain comments, trailing commas and JSON5 syntax.
 #[pyfunction]
-#[pyo3(signature = (before_path, after_path, add_idx=None))]
-pub fn compare_json_files(before_path: &str, after_path: &str, add_idx: Option<bool>) -> PyResult<String> {
+#[pyo3(signature = (before_path, after_path, add_idx=None, lenient=None, comparators=None))]
+pub fn compare_json_files(py: Python, before_path: &str, after_path: &str, add_idx: Option<bool>,
+                          lenient: Option<bool>, comparators: PyComparators) -> PyResult<String> {
     // Read files
-    let before_text = fs::read_to_string(before_path)
+    let before_data = fs::read(before_path)
         .map_err(|e| PyValueError::new_err(format!("Failed to read file {}: {}", before_path, e)))?;
     
-    let after_text = fs::read_to_string(after_path)
+    let after_data = fs::read(after_path)
         .map_err(|e| PyValueError::new_err(format!("Failed to read file {}: {}", after_path, e)))?;
     
-    // Parse JSON
-    let before: Value = serde_json::from_str(&before_text)
-        .map_err(|e| PyValueError::new_err(format!("Failed to parse JSON from {}: {}", before_path, e)))?;
-    
-    let after: Value = serde_json::from_str(&after_text)
-        .map_err(|e| PyValueError::new_err(format!("Failed to parse JSON from {}: {}", after_path, e)))?;
+    // Parse documents, detecting the format by extension or contents
+    let before = parse_file(&before_data, before_path, lenient.unwrap_or(false))?;
+    let after = parse_file(&after_data, after_path, lenient.unwrap_or(false))?;
     
     // Compare and format
-    let result = compare_json(&before, &after, add_idx);
+    let result = compare_with_comparators(py, &before, &after, add_idx, comparators)?;
     let formatted = format_diff_to_string(&result, 0);
     
     Ok(formatted)
 }
+
+/// Compute change statistics for two JSON strings
+///
+/// Returns a dict with the `total` counts of added, removed, modified and moved
+/// values, the counts `by_key` for each top-level key, and the `most_changed`
+/// and `deepest` subtrees.
+#[pyfunction]
+#[pyo3(signature = (before_json, after_json, lenient=None))]
+pub fn compare_json_stats(py: Python, before_json: &str, after_json: &str, lenient: Option<bool>) -> PyResult<PyObject> {
+    let format = if lenient.unwrap_or(false) { InputFormat::Json5 } else { InputFormat::Json };
+    
+    let before = parse_document(before_json, format)
+        .map_err(|e| PyValueError::new_err(format!("Failed to parse 'before' JSON: {}", e)))?;
+    
+    let after = parse_document(after_json, format)
+        .map_err(|e| PyValueError::new_err(format!("Failed to parse 'after' JSON: {}", e)))?;
+    
+    let stats = DiffStats::compute(&before, &after, &DiffOptions::default());
+    
+    // Convert to a dict using Python's json module
+    let json = PyModule::import(py, "json")?;
+    let dict = json.getattr("loads")?.call1((stats.to_json().to_string(),))?;
+    Ok(dict.unbind())
+}
+
+// Compare two documents, consulting the Python comparators. The GIL is released
+// during the comparison so that worker threads can call back into Python; the
+// first exception raised by a callback is re-raised afterwards.
+fn compare_with_comparators(py: Python, before: &Value, after: &Value, add_idx: Option<bool>,
+                            comparators: PyComparators) -> PyResult<Value> {
+    let error: Arc<Mutex<Option<PyErr>>> = Arc::new(Mutex::new(None));
+    let mut options = DiffOptions {
+        add_idx: add_idx.unwrap_or(false),
+        ..DiffOptions::default()
+    };
+    for (selector, compare) in comparators.unwrap_or_default() {
+        options.comparators.push(python_comparator(py, selector, compare, &error)?);
+    }
+
+    let result = py.allow_threads(|| compare_json_with_options(before, after, &options));
+    let raised = error.lock().ok().and_then(|mut error| error.take());
+    match raised {
+        Some(e) => Err(e),
+        None => Ok(result),
+    }
+}
+
+// Comparator calling a Python `compare(before, after)`, selected by a path
+// pattern string or by a Python predicate `(path, before, after)`
+fn python_comparator(py: Python, selector: PyObject, compare: PyObject,
+                     error: &Arc<Mutex<Option<PyErr>>>) -> PyResult<Comparator> {
+    let compare_error = Arc::clone(error);
+    let compare = move |before: &Value, after: &Value| {
+        let result = Python::with_gil(|py| {
+            let outcome = compare.call1(py, (to_python(py, before)?, to_python(py, after)?))?;
+            let outcome = outcome.bind(py);
+            if outcome.is_instance_of::<PyBool>() {
+                Ok(if outcome.extract::<bool>()? { Comparison::Equal } else { Comparison::Different })
+            } else if outcome.is_instance_of::<PyString>() {
+                Ok(Comparison::Custom(outcome.extract::<String>()?))
+            } else {
+                Err(PyTypeError::new_err("Comparators must return a bool or a string"))
+            }
+        });
+        result.unwrap_or_else(|e| {
+            record_error(&compare_error, e);
+            Comparison::Different
+        })
+    };
+
+    let selector_ref = selector.bind(py);
+    if selector_ref.is_instance_of::<PyString>() {
+        let pattern = PathPattern::new(&selector_ref.extract::<String>()?).map_err(PyValueError::new_err)?;
+        return Ok(Comparator::at_path(pattern, compare));
+    }
+    if !selector_ref.is_callable() {
+        return Err(PyTypeError::new_err("Comparator selectors must be a path pattern or a callable"));
+    }
+
+    let predicate_error = Arc::clone(error);
+    let predicate = move |path: &str, before: &Value, after: &Value| {
+        let result = Python::with_gil(|py| {
+            selector.call1(py, (path, to_python(py, before)?, to_python(py, after)?))?
+                .bind(py)
+                .is_truthy()
+        });
+        result.unwrap_or_else(|e| {
+            record_error(&predicate_error, e);
+            false
+        })
+    };
+    Ok(Comparator::when(predicate, compare))
+}
+
+// Keep the first exception raised by a Python callback
+fn record_error(error: &Mutex<Option<PyErr>>, e: PyErr) {
+    if let Ok(mut error) = error.lock() {
+        error.get_or_insert(e);
+    }
+}
+
+// Convert a JSON value to the equivalent Python object
+fn to_python(py: Python, value: &Value) -> PyResult<PyObject> {
+    let json = PyModule::import(py, "json")?;
+    Ok(json.getattr("loads")?.call1((value.to_string(),))?.unbind())
+}
+
+// Parse a file's contents in the format detected from its extension or contents
+fn parse_file(data: &[u8], path: &str, lenient: bool) -> PyResult<Value> {
+    let detected = InputFormat::from_path(path)
+        .or_else(|| InputFormat::detect(data))
+        .unwrap_or(InputFormat::Json);
+    let format = match detected {
+        InputFormat::Json if lenient => InputFormat::Json5,
+        format => format,
+    };
+    parse_document_bytes(data, format)
+        .map_err(|e| PyValueError::new_err(format!("Failed to parse {} from {}: {}", format, path, e)))
+}
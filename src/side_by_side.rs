@@ -0,0 +1,82 @@
+//! Side-by-side rendering of two JSON structures
+//!
+//! Both documents are pretty-printed in two columns, the original on the left
+//! and the modified on the right, with their lines aligned by matched keys and
+//! array elements. A gutter between the columns marks each row like `diff -y`:
+//! `|` for changed, `<` for removed and `>` for added lines.
+
+use serde_json::Value;
+
+use crate::align::{align_documents, RowKind};
+use crate::options::DiffOptions;
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const RESET: &str = "\x1b[0m";
+
+// Narrowest column that still shows some of each line
+const MIN_COLUMN_WIDTH: usize = 10;
+
+/// Render two JSON structures side by side
+///
+/// # Arguments
+/// * `before` - The original JSON structure, shown on the left
+/// * `after` - The modified JSON structure, shown on the right
+/// * `options` - Options of the element matching used to align arrays
+/// * `width` - Total width of the output in characters; longer lines are
+///   wrapped within their column
+/// * `color` - Highlight removed and added lines with ANSI colors
+pub fn format_side_by_side(before: &Value, after: &Value, options: &DiffOptions, width: usize, color: bool) -> String {
+    let rows = align_documents(before, after, options);
+
+    // Two columns separated by a three-character gutter
+    let column = (width.saturating_sub(3) / 2).max(MIN_COLUMN_WIDTH);
+    let mut result = String::new();
+
+    for row in &rows {
+        let left = wrap(row.left.as_deref().unwrap_or(""), column);
+        let right = wrap(row.right.as_deref().unwrap_or(""), column);
+        let gutter = match row.kind {
+            RowKind::Same => ' ',
+            RowKind::Changed => '|',
+            RowKind::Removed => '<',
+            RowKind::Added => '>',
+        };
+        let (left_color, right_color) = match row.kind {
+            _ if !color => (None, None),
+            RowKind::Same => (None, None),
+            RowKind::Changed => (Some(RED), Some(GREEN)),
+            RowKind::Removed => (Some(RED), None),
+            RowKind::Added => (None, Some(GREEN)),
+        };
+
+        for i in 0..left.len().max(right.len()) {
+            let left_part = left.get(i).map(String::as_str).unwrap_or("");
+            let right_part = right.get(i).map(String::as_str).unwrap_or("");
+            let padding = " ".repeat(column - left_part.chars().count());
+            let mark = if i == 0 { gutter } else { ' ' };
+
+            let line = format!("{}{} {} {}", paint(left_part, left_color), padding, mark, paint(right_part, right_color));
+            result.push_str(line.trim_end());
+            result.push('\n');
+        }
+    }
+
+    result
+}
+
+// Split a line into pieces of at most `width` characters
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+    chars.chunks(width).map(|chunk| chunk.iter().collect()).collect()
+}
+
+fn paint(text: &str, color: Option<&str>) -> String {
+    match color {
+        Some(code) if !text.is_empty() => format!("{}{}{}", code, text, RESET),
+        _ => text.to_string(),
+    }
+}
@@ -0,0 +1,179 @@
+//! Summary statistics of the changes between two JSON structures
+
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::changes::{collect_changes, Change, ChangeKind};
+use crate::options::DiffOptions;
+
+// Number of entries in the deepest and most changed subtree lists
+const TOP_ENTRIES: usize = 5;
+
+/// Number of changes of each kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChangeCounts {
+    pub added: usize,
+    pub removed: usize,
+    pub modified: usize,
+    pub moved: usize,
+    pub cosmetic: usize,
+}
+
+impl ChangeCounts {
+    /// Total number of changes
+    pub fn total(&self) -> usize {
+        self.added + self.removed + self.modified + self.moved + self.cosmetic
+    }
+
+    fn count(&mut self, kind: &ChangeKind) {
+        match kind {
+            ChangeKind::Added => self.added += 1,
+            ChangeKind::Removed => self.removed += 1,
+            ChangeKind::Modified => self.modified += 1,
+            ChangeKind::Moved { .. } => self.moved += 1,
+            ChangeKind::Cosmetic => self.cosmetic += 1,
+        }
+    }
+
+    fn to_json(self) -> Value {
+        json!({
+            "added": self.added,
+            "removed": self.removed,
+            "modified": self.modified,
+            "moved": self.moved,
+            "cosmetic": self.cosmetic,
+        })
+    }
+}
+
+/// Statistics of the changes between two JSON structures
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiffStats {
+    /// Changes in the whole structure
+    pub total: ChangeCounts,
+    /// Changes below each top-level key or array index; changes of the root
+    /// value itself are counted under the empty key
+    pub by_key: BTreeMap<String, ChangeCounts>,
+    /// Containers holding the most changes, as JSON Pointers with their
+    /// number of changes, most changed first
+    pub most_changed: Vec<(String, usize)>,
+    /// Most deeply nested changes, as JSON Pointers with their depth, deepest
+    /// first
+    pub deepest: Vec<(String, usize)>,
+}
+
+impl DiffStats {
+    /// Compute the statistics of the changes between two JSON structures
+    pub fn compute(before: &Value, after: &Value, options: &DiffOptions) -> DiffStats {
+        DiffStats::from_changes(&collect_changes(before, after, options))
+    }
+
+    /// Compute the statistics of a list of changes
+    pub fn from_changes(changes: &[Change]) -> DiffStats {
+        let mut stats = DiffStats::default();
+        let mut subtrees: HashMap<&str, usize> = HashMap::new();
+        let mut depths: Vec<(String, usize)> = Vec::new();
+
+        for change in changes {
+            stats.total.count(&change.kind);
+
+            let segments: Vec<&str> = change.path.split('/').skip(1).collect();
+            let top_key = segments.first().map(|key| unescape_pointer(key)).unwrap_or_default();
+            stats.by_key.entry(top_key).or_default().count(&change.kind);
+
+            // Every enclosing container below the root
+            let mut end = 0;
+            for segment in segments.iter().take(segments.len().saturating_sub(1)) {
+                end += segment.len() + 1;
+                *subtrees.entry(&change.path[..end]).or_default() += 1;
+            }
+            depths.push((change.path.clone(), segments.len()));
+        }
+
+        let mut most_changed: Vec<(String, usize)> = subtrees.into_iter()
+            .map(|(path, count)| (path.to_string(), count))
+            .collect();
+        most_changed.sort_by(|(a_path, a_count), (b_path, b_count)| b_count.cmp(a_count).then_with(|| a_path.cmp(b_path)));
+        most_changed.truncate(TOP_ENTRIES);
+        stats.most_changed = most_changed;
+
+        depths.sort_by(|(a_path, a_depth), (b_path, b_depth)| b_depth.cmp(a_depth).then_with(|| a_path.cmp(b_path)));
+        depths.truncate(TOP_ENTRIES);
+        stats.deepest = depths;
+
+        stats
+    }
+
+    /// Statistics as a JSON object
+    pub fn to_json(&self) -> Value {
+        let by_key: serde_json::Map<String, Value> = self.by_key.iter()
+            .map(|(key, counts)| (key.clone(), counts.to_json()))
+            .collect();
+        let entries = |list: &[(String, usize)], field: &str| -> Vec<Value> {
+            list.iter().map(|(path, n)| json!({ "path": path, field: n })).collect()
+        };
+
+        json!({
+            "total": self.total.to_json(),
+            "by_key": by_key,
+            "most_changed": entries(&self.most_changed, "changes"),
+            "deepest": entries(&self.deepest, "depth"),
+        })
+    }
+}
+
+impl fmt::Display for DiffStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.total.total() == 0 {
+            return write!(f, "No changes");
+        }
+        let noun = if self.total.total() == 1 { "change" } else { "changes" };
+        write!(f, "{} {}: {}", self.total.total(), noun, describe_counts(&self.total))?;
+
+        let display_key = |key: &String| if key.is_empty() { "(root)".to_string() } else { key.clone() };
+        let width = self.by_key.keys().map(|key| display_key(key).chars().count()).max().unwrap_or(0);
+        write!(f, "\n\nBy top-level key:")?;
+        for (key, counts) in &self.by_key {
+            write!(f, "\n  {:width$}  {} ({})", display_key(key), counts.total(), describe_counts(counts), width = width)?;
+        }
+
+        if !self.most_changed.is_empty() {
+            let width = self.most_changed.iter().map(|(path, _)| path.chars().count()).max().unwrap_or(0);
+            write!(f, "\n\nMost changed subtrees:")?;
+            for (path, count) in &self.most_changed {
+                write!(f, "\n  {:width$}  {}", path, count, width = width)?;
+            }
+        }
+
+        let display_path = |path: &str| if path.is_empty() { "(root)".to_string() } else { path.to_string() };
+        let width = self.deepest.iter().map(|(path, _)| display_path(path).chars().count()).max().unwrap_or(0);
+        write!(f, "\n\nDeepest changes:")?;
+        for (path, depth) in &self.deepest {
+            write!(f, "\n  {:width$}  (depth {})", display_path(path), depth, width = width)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Comma-separated non-zero counts, e.g. "2 added, 1 moved"
+fn describe_counts(counts: &ChangeCounts) -> String {
+    let parts: Vec<String> = [
+        (counts.added, "added"),
+        (counts.removed, "removed"),
+        (counts.modified, "modified"),
+        (counts.moved, "moved"),
+        (counts.cosmetic, "cosmetic"),
+    ]
+    .iter()
+    .filter(|(n, _)| *n > 0)
+    .map(|(n, label)| format!("{} {}", n, label))
+    .collect();
+
+    parts.join(", ")
+}
+
+fn unescape_pointer(segment: &str) -> String {
+    segment.replace("~1", "/").replace("~0", "~")
+}
@@ -0,0 +1,678 @@
+//! Streaming comparison of large JSON documents
+//!
+//! Instead of loading both documents into memory, the top-level array or object
+//! of each document is read element by element. Elements are paired as soon as
+//! their counterpart has been read and the comparison result is written out
+//! immediately, so memory use is bounded by the size of the unmatched window
+//! rather than by the size of the documents.
+//!
+//! Each pair of elements is compared at its own path, the index in the
+//! modified document or the key, so path patterns in the options select the
+//! same values as in a comparison of the loaded documents.
+//!
+//! Array elements are paired by identical content or identical identifier while
+//! streaming; fuzzy identifier matching is only applied to the elements still
+//! unmatched when both documents have been read. Elements are emitted in the
+//! order in which they were paired. Documents whose top-level values are not
+//! both arrays or both objects are loaded fully and compared with
+//! [`compare_json_with_options`].
+
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead, Write};
+
+use crate::changes::{differs, escape_pointer, has_differences};
+use crate::core::{compare_at, compare_json_with_options, entries_mut, format_diff_to_string, mark_added, mark_deleted};
+use crate::matching::{array_semantics, identity_key, match_arrays, value_hash};
+use crate::options::DiffOptions;
+
+/// Compare two JSON documents read incrementally and write the formatted
+/// differences to `out`
+///
+/// # Arguments
+/// * `before` - Reader for the original JSON document
+/// * `after` - Reader for the modified JSON document
+/// * `options` - Comparison options; [`DiffOptions::stream_window`] bounds the
+///   number of unmatched array elements held in memory per side
+/// * `out` - Destination for the formatted output
+///
+/// # Returns
+/// Whether the documents differ, or an error if either document cannot be read
+/// or is not valid JSON, or if [`DiffOptions::array_semantics`] selects the
+/// top-level array, whose elements are always paired by content and identifier
+pub fn compare_json_streaming<B, A, W>(before: B, after: A, options: &DiffOptions, out: &mut W) -> io::Result<bool>
+where
+    B: BufRead,
+    A: BufRead,
+    W: Write,
+{
+    let mut before = JsonReader::new(before, "before");
+    let mut after = JsonReader::new(after, "after");
+
+    let changed = match (before.peek()?, after.peek()?) {
+        (Some(b'['), Some(b'[')) => stream_arrays(&mut before, &mut after, options, out)?,
+        (Some(b'{'), Some(b'{')) => stream_objects(&mut before, &mut after, options, out)?,
+        _ => {
+            let before_value = before.read_value()?;
+            let after_value = after.read_value()?;
+            let result = compare_json_with_options(&before_value, &after_value, options);
+            write!(out, "{}", format_diff_to_string(&result, 0))?;
+            has_differences(&before_value, &after_value, options)
+        },
+    };
+
+    before.expect_end()?;
+    after.expect_end()?;
+    writeln!(out)?;
+    Ok(changed)
+}
+
+// Stream two top-level arrays, pairing elements within a bounded window
+fn stream_arrays<B: BufRead, A: BufRead, W: Write>(before: &mut JsonReader<B>, after: &mut JsonReader<A>,
+                                                   options: &DiffOptions, out: &mut W) -> io::Result<bool> {
+    // Elements are paired by content and identifier, whatever the semantics
+    if array_semantics("", options).is_some() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                  "array semantics of the top-level array are not supported in streaming mode"));
+    }
+    before.expect(b'[')?;
+    after.expect(b'[')?;
+
+    let mut emitter = Emitter::new(out, '[', ']');
+    let mut pending_before = Pending::default();
+    let mut pending_after = Pending::default();
+    let mut object_index = 0;
+    let mut before_index = 0;
+    let mut after_index = 0;
+    let mut before_done = false;
+    let mut after_done = false;
+
+    // Paired elements are compared at the path of the element in `after`
+    while !before_done || !after_done {
+        if !before_done {
+            match before.next_element()? {
+                Some(item) => {
+                    match pending_after.take_match(&item) {
+                        Some((j, after_item)) => {
+                            emitter.emit_pair(&format!("/{}", j), None, &item, &after_item, options, &mut object_index)?
+                        },
+                        None => pending_before.push(before_index, item),
+                    }
+                    before_index += 1;
+                },
+                None => before_done = true,
+            }
+        }
+        if !after_done {
+            match after.next_element()? {
+                Some(item) => {
+                    match pending_before.take_match(&item) {
+                        Some((_, before_item)) => {
+                            let path = format!("/{}", after_index);
+                            emitter.emit_pair(&path, None, &before_item, &item, options, &mut object_index)?
+                        },
+                        None => pending_after.push(after_index, item),
+                    }
+                    after_index += 1;
+                },
+                None => after_done = true,
+            }
+        }
+
+        // Elements that fell out of the window are reported as deleted or added
+        while pending_before.len() > options.stream_window {
+            if let Some((_, item)) = pending_before.pop_oldest() {
+                emitter.emit_deleted(None, item, options, &mut object_index)?;
+            }
+        }
+        while pending_after.len() > options.stream_window {
+            if let Some((_, item)) = pending_after.pop_oldest() {
+                emitter.emit_added(None, item, options, &mut object_index)?;
+            }
+        }
+    }
+
+    // Fuzzy matching for whatever is left
+    let (_, remaining_before): (Vec<usize>, Vec<Value>) = pending_before.into_values().into_iter().unzip();
+    let (after_indices, remaining_after): (Vec<usize>, Vec<Value>) = pending_after.into_values().into_iter().unzip();
+    let matches = match_arrays(&remaining_before, &remaining_after, "", options);
+    let mut matched_after = vec![false; remaining_after.len()];
+
+    for (item, match_idx) in remaining_before.into_iter().zip(matches) {
+        match match_idx {
+            Some(idx) => {
+                matched_after[idx] = true;
+                let path = format!("/{}", after_indices[idx]);
+                emitter.emit_pair(&path, None, &item, &remaining_after[idx], options, &mut object_index)?;
+            },
+            None => emitter.emit_deleted(None, item, options, &mut object_index)?,
+        }
+    }
+    for (item, matched) in remaining_after.into_iter().zip(matched_after) {
+        if !matched {
+            emitter.emit_added(None, item, options, &mut object_index)?;
+        }
+    }
+
+    emitter.close()
+}
+
+// Stream two top-level objects, pairing entries by key
+fn stream_objects<B: BufRead, A: BufRead, W: Write>(before: &mut JsonReader<B>, after: &mut JsonReader<A>,
+                                                    options: &DiffOptions, out: &mut W) -> io::Result<bool> {
+    before.expect(b'{')?;
+    after.expect(b'{')?;
+
+    let mut emitter = Emitter::new(out, '{', '}');
+    let mut pending_before: HashMap<String, Value> = HashMap::new();
+    let mut pending_after: HashMap<String, Value> = HashMap::new();
+    let mut before_order: Vec<String> = Vec::new();
+    let mut after_order: Vec<String> = Vec::new();
+    let mut unused_index = 0;
+    let mut before_done = false;
+    let mut after_done = false;
+
+    while !before_done || !after_done {
+        if !before_done {
+            match before.next_entry()? {
+                Some((key, item)) => match pending_after.remove(&key) {
+                    Some(after_item) => {
+                        let path = format!("/{}", escape_pointer(&key));
+                        emitter.emit_pair(&path, Some(&key), &item, &after_item, options, &mut unused_index)?
+                    },
+                    None => {
+                        before_order.push(key.clone());
+                        pending_before.insert(key, item);
+                    },
+                },
+                None => before_done = true,
+            }
+        }
+        if !after_done {
+            match after.next_entry()? {
+                Some((key, item)) => match pending_before.remove(&key) {
+                    Some(before_item) => {
+                        let path = format!("/{}", escape_pointer(&key));
+                        emitter.emit_pair(&path, Some(&key), &before_item, &item, options, &mut unused_index)?
+                    },
+                    None => {
+                        after_order.push(key.clone());
+                        pending_after.insert(key, item);
+                    },
+                },
+                None => after_done = true,
+            }
+        }
+    }
+
+    for key in before_order {
+        if let Some(item) = pending_before.remove(&key) {
+            emitter.emit_deleted(Some(&key), item, options, &mut unused_index)?;
+        }
+    }
+    for key in after_order {
+        if let Some(item) = pending_after.remove(&key) {
+            emitter.emit_added(Some(&key), item, options, &mut unused_index)?;
+        }
+    }
+
+    emitter.close()
+}
+
+// Writes the elements of the top-level container as they become available,
+// using the same layout as `format_diff_to_string`
+struct Emitter<'a, W: Write> {
+    out: &'a mut W,
+    open: char,
+    close: char,
+    empty: bool,
+    changed: bool,
+}
+
+impl<'a, W: Write> Emitter<'a, W> {
+    fn new(out: &'a mut W, open: char, close: char) -> Self {
+        Emitter { out, open, close, empty: true, changed: false }
+    }
+
+    fn emit(&mut self, key: Option<&str>, value: Value) -> io::Result<()> {
+        if self.empty {
+            write!(self.out, "{}\n  ", self.open)?;
+            self.empty = false;
+        } else {
+            write!(self.out, ",\n  ")?;
+        }
+        if let Some(key) = key {
+            write!(self.out, "\"{}\": ", key)?;
+        }
+        write!(self.out, "{}", format_diff_to_string(&value, 2))
+    }
+
+    // Compare a pair of elements found at the JSON Pointer `path`
+    fn emit_pair(&mut self, path: &str, key: Option<&str>, before: &Value, after: &Value, options: &DiffOptions,
+                 object_index: &mut usize) -> io::Result<()> {
+        let compared = compare_at(before, after, path, options);
+        if !self.changed && differs(before, after, path, options) {
+            self.changed = true;
+        }
+        let with_index = key.is_none() && before.is_object();
+        self.emit_indexed(key, compared, with_index, options, object_index)
+    }
+
+    fn emit_deleted(&mut self, key: Option<&str>, item: Value, options: &DiffOptions,
+                    object_index: &mut usize) -> io::Result<()> {
+        let with_index = key.is_none() && item.is_object();
+        self.changed = true;
+        self.emit_indexed(key, mark_deleted(item), with_index, options, object_index)
+    }
+
+    fn emit_added(&mut self, key: Option<&str>, item: Value, options: &DiffOptions,
+                  object_index: &mut usize) -> io::Result<()> {
+        let with_index = key.is_none() && item.is_object();
+        self.changed = true;
+        self.emit_indexed(key, mark_added(item), with_index, options, object_index)
+    }
+
+    fn emit_indexed(&mut self, key: Option<&str>, mut value: Value, with_index: bool, options: &DiffOptions,
+                    object_index: &mut usize) -> io::Result<()> {
+        if with_index {
+            if options.add_idx {
+                if let Some(obj) = entries_mut(&mut value) {
+                    obj.insert("idx".to_string(), Value::Number(serde_json::Number::from(*object_index)));
+                }
+            }
+            *object_index += 1;
+        }
+        self.emit(key, value)
+    }
+
+    // Close the container, returning whether any difference was emitted
+    fn close(self) -> io::Result<bool> {
+        if self.empty {
+            write!(self.out, "{}{}", self.open, self.close)?;
+        } else {
+            write!(self.out, "\n{}", self.close)?;
+        }
+        Ok(self.changed)
+    }
+}
+
+// Array elements read from one side that have no counterpart yet, indexed by
+// content hash and identifier; each element is known by its index in its
+// document
+#[derive(Default)]
+struct Pending {
+    items: HashMap<usize, Value>,
+    order: VecDeque<usize>,
+    by_hash: HashMap<u64, Vec<usize>>,
+    by_identifier: HashMap<String, Vec<usize>>,
+}
+
+impl Pending {
+    fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    fn push(&mut self, seq: usize, item: Value) {
+        self.by_hash.entry(value_hash(&item)).or_default().push(seq);
+        if let Some(id) = identity_key(&item) {
+            self.by_identifier.entry(id).or_default().push(seq);
+        }
+        self.order.push_back(seq);
+        self.items.insert(seq, item);
+    }
+
+    // Remove and return the pending element with the same content as `item`,
+    // or failing that the one with the same identifier
+    fn take_match(&mut self, item: &Value) -> Option<(usize, Value)> {
+        let exact = self.by_hash.get(&value_hash(item))
+            .and_then(|seqs| seqs.iter().copied().find(|seq| self.items.get(seq) == Some(item)));
+
+        let seq = exact.or_else(|| {
+            identity_key(item)
+                .and_then(|id| self.by_identifier.get(&id))
+                .and_then(|seqs| seqs.first().copied())
+        })?;
+
+        self.remove(seq)
+    }
+
+    fn pop_oldest(&mut self) -> Option<(usize, Value)> {
+        while let Some(seq) = self.order.pop_front() {
+            if self.items.contains_key(&seq) {
+                return self.remove(seq);
+            }
+        }
+        None
+    }
+
+    fn remove(&mut self, seq: usize) -> Option<(usize, Value)> {
+        let item = self.items.remove(&seq)?;
+
+        let hash = value_hash(&item);
+        if let Some(seqs) = self.by_hash.get_mut(&hash) {
+            seqs.retain(|s| *s != seq);
+            if seqs.is_empty() {
+                self.by_hash.remove(&hash);
+            }
+        }
+        if let Some(id) = identity_key(&item) {
+            if let Some(seqs) = self.by_identifier.get_mut(&id) {
+                seqs.retain(|s| *s != seq);
+                if seqs.is_empty() {
+                    self.by_identifier.remove(&id);
+                }
+            }
+        }
+
+        Some((seq, item))
+    }
+
+    fn into_values(mut self) -> Vec<(usize, Value)> {
+        let order: Vec<usize> = self.order.drain(..).collect();
+        order.into_iter()
+            .filter_map(|seq| self.items.remove(&seq).map(|item| (seq, item)))
+            .collect()
+    }
+}
+
+// Minimal incremental reader that splits a JSON document into the elements of
+// its top-level container without parsing the whole document
+struct JsonReader<R: BufRead> {
+    reader: R,
+    name: &'static str,
+    first: bool,
+}
+
+impl<R: BufRead> JsonReader<R> {
+    fn new(reader: R, name: &'static str) -> Self {
+        JsonReader { reader, name, first: true }
+    }
+
+    fn error(&self, message: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, format!("Failed to parse '{}' JSON: {}", self.name, message))
+    }
+
+    // Next byte without consuming it, skipping whitespace
+    fn peek(&mut self) -> io::Result<Option<u8>> {
+        loop {
+            let byte = match self.reader.fill_buf()?.first() {
+                Some(byte) => *byte,
+                None => return Ok(None),
+            };
+            if byte.is_ascii_whitespace() {
+                self.reader.consume(1);
+            } else {
+                return Ok(Some(byte));
+            }
+        }
+    }
+
+    fn next_byte(&mut self) -> io::Result<Option<u8>> {
+        let byte = self.reader.fill_buf()?.first().copied();
+        if byte.is_some() {
+            self.reader.consume(1);
+        }
+        Ok(byte)
+    }
+
+    fn expect(&mut self, expected: u8) -> io::Result<()> {
+        match self.peek()? {
+            Some(byte) if byte == expected => {
+                self.reader.consume(1);
+                Ok(())
+            },
+            Some(byte) => Err(self.error(&format!("expected '{}', found '{}'", expected as char, byte as char))),
+            None => Err(self.error(&format!("expected '{}', found end of input", expected as char))),
+        }
+    }
+
+    fn expect_end(&mut self) -> io::Result<()> {
+        match self.peek()? {
+            None => Ok(()),
+            Some(byte) => Err(self.error(&format!("trailing characters starting with '{}'", byte as char))),
+        }
+    }
+
+    // Read the raw bytes of one complete JSON value
+    fn read_raw(&mut self, buf: &mut Vec<u8>) -> io::Result<()> {
+        match self.peek()? {
+            None => Err(self.error("unexpected end of input")),
+            Some(b'"') => self.read_raw_string(buf),
+            Some(b'{') | Some(b'[') => {
+                let mut depth = 0usize;
+                loop {
+                    let byte = match self.peek_raw()? {
+                        Some(byte) => byte,
+                        None => return Err(self.error("unexpected end of input")),
+                    };
+                    match byte {
+                        b'"' => {
+                            self.read_raw_string(buf)?;
+                            continue;
+                        },
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => depth -= 1,
+                        _ => {},
+                    }
+                    buf.push(byte);
+                    self.reader.consume(1);
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+            },
+            Some(_) => {
+                while let Some(byte) = self.peek_raw()? {
+                    if byte.is_ascii_whitespace() || matches!(byte, b',' | b']' | b'}' | b':') {
+                        break;
+                    }
+                    buf.push(byte);
+                    self.reader.consume(1);
+                }
+                Ok(())
+            },
+        }
+    }
+
+    // Next byte without consuming it or skipping whitespace
+    fn peek_raw(&mut self) -> io::Result<Option<u8>> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    fn read_raw_string(&mut self, buf: &mut Vec<u8>) -> io::Result<()> {
+        // Opening quote
+        if let Some(byte) = self.next_byte()? {
+            buf.push(byte);
+        }
+        let mut escaped = false;
+        loop {
+            let byte = match self.next_byte()? {
+                Some(byte) => byte,
+                None => return Err(self.error("unterminated string")),
+            };
+            buf.push(byte);
+            if escaped {
+                escaped = false;
+            } else if byte == b'\\' {
+                escaped = true;
+            } else if byte == b'"' {
+                return Ok(());
+            }
+        }
+    }
+
+    fn read_value(&mut self) -> io::Result<Value> {
+        let mut buf = Vec::new();
+        self.read_raw(&mut buf)?;
+        serde_json::from_slice(&buf).map_err(|e| self.error(&e.to_string()))
+    }
+
+    // Read the separator before the next element of the current container,
+    // returning false at its closing bracket
+    fn advance(&mut self, close: u8) -> io::Result<bool> {
+        if self.peek()? == Some(close) {
+            self.reader.consume(1);
+            return Ok(false);
+        }
+        if !self.first {
+            self.expect(b',')?;
+        }
+        self.first = false;
+        Ok(true)
+    }
+
+    fn next_element(&mut self) -> io::Result<Option<Value>> {
+        if !self.advance(b']')? {
+            return Ok(None);
+        }
+        self.read_value().map(Some)
+    }
+
+    fn next_entry(&mut self) -> io::Result<Option<(String, Value)>> {
+        if !self.advance(b'}')? {
+            return Ok(None);
+        }
+        let key = match self.read_value()? {
+            Value::String(key) => key,
+            other => return Err(self.error(&format!("expected object key, found {}", other))),
+        };
+        self.expect(b':')?;
+        let value = self.read_value()?;
+        Ok(Some((key, value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::ArraySemantics;
+    use crate::pattern::PathPattern;
+    use serde_json::json;
+    use std::io::BufReader;
+
+    // Reader returning one byte per read, so that every token is split
+    fn reader(text: &str) -> JsonReader<BufReader<&[u8]>> {
+        JsonReader::new(BufReader::with_capacity(1, text.as_bytes()), "test")
+    }
+
+    fn elements(text: &str) -> Vec<Value> {
+        let mut reader = reader(text);
+        reader.expect(b'[').unwrap();
+        let mut items = Vec::new();
+        while let Some(item) = reader.next_element().unwrap() {
+            items.push(item);
+        }
+        reader.expect_end().unwrap();
+        items
+    }
+
+    fn stream(before: &str, after: &str, options: &DiffOptions) -> (bool, String) {
+        let mut out = Vec::new();
+        let changed = compare_json_streaming(BufReader::with_capacity(1, before.as_bytes()),
+                                             BufReader::with_capacity(1, after.as_bytes()), options, &mut out).unwrap();
+        (changed, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn strings_with_escapes_across_reads() {
+        let items = elements(r#" [ "a\"b" , "c\\" , "]é}", "\\\"" ] "#);
+        assert_eq!(items, vec![json!("a\"b"), json!("c\\"), json!("]é}"), json!("\\\"")]);
+    }
+
+    #[test]
+    fn nested_containers() {
+        let items = elements(r#"[{"a": [1, {"b": "]}"}], "c": {}}, [[], [[2]]], 3, true, null]"#);
+        assert_eq!(items, vec![
+            json!({"a": [1, {"b": "]}"}], "c": {}}),
+            json!([[], [[2]]]),
+            json!(3),
+            json!(true),
+            json!(null),
+        ]);
+
+        let mut reader = reader(r#"{"k": {"x": [1, "}"]}, "l": -1.5e3}"#);
+        reader.expect(b'{').unwrap();
+        assert_eq!(reader.next_entry().unwrap(), Some(("k".to_string(), json!({"x": [1, "}"]}))));
+        assert_eq!(reader.next_entry().unwrap(), Some(("l".to_string(), json!(-1.5e3))));
+        assert_eq!(reader.next_entry().unwrap(), None);
+    }
+
+    #[test]
+    fn invalid_documents_are_errors() {
+        let mut out = Vec::new();
+        let result = compare_json_streaming(r#"["a""#.as_bytes(), "[]".as_bytes(), &DiffOptions::default(), &mut out);
+        assert!(result.is_err());
+        let result = compare_json_streaming("[1] 2".as_bytes(), "[1]".as_bytes(), &DiffOptions::default(), &mut out);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn elements_out_of_the_window_are_deleted_and_added() {
+        let before = r#"["a", "b", "c"]"#;
+        let after = r#"["d", "e", "a"]"#;
+
+        let (changed, output) = stream(before, after, &DiffOptions::default());
+        assert!(changed);
+        assert!(!output.contains("\"a\" [-]"), "{}", output);
+
+        let options = DiffOptions { stream_window: 1, ..DiffOptions::default() };
+        let (changed, output) = stream(before, after, &options);
+        assert!(changed);
+        assert!(output.contains("\"a\" [-]") && output.contains("\"a\" [+]"), "{}", output);
+    }
+
+    #[test]
+    fn path_scoped_options_apply_to_the_paths_of_elements() {
+        let options = DiffOptions {
+            coerce_types: vec![PathPattern::new("/1/count").unwrap(), PathPattern::new("/meta/count").unwrap()],
+            array_semantics: vec![(PathPattern::new("/*/tags").unwrap(), ArraySemantics::Unordered)],
+            ..DiffOptions::default()
+        };
+        let cases = [
+            (r#"[{"id": "a", "count": "1"}, {"id": "b", "count": "2", "tags": ["x", "y"]}]"#,
+             r#"[{"id": "a", "count": 1}, {"id": "b", "count": 2, "tags": ["y", "x"]}]"#),
+            (r#"[{"id": "a", "count": 1}, {"id": "b", "count": "2"}]"#,
+             r#"[{"id": "a", "count": 1}, {"id": "b", "count": 2}]"#),
+            (r#"{"data": {"count": "3"}, "meta": {"count": "3", "tags": ["x", "y"]}}"#,
+             r#"{"data": {"count": 3}, "meta": {"count": 3, "tags": ["y", "x"]}}"#),
+        ];
+        for (before_text, after_text) in cases {
+            let (changed, output) = stream(before_text, after_text, &options);
+            let before: Value = serde_json::from_str(before_text).unwrap();
+            let after: Value = serde_json::from_str(after_text).unwrap();
+            let expected = compare_json_with_options(&before, &after, &options);
+            assert_eq!(changed, has_differences(&before, &after, &options), "{}", before_text);
+            assert_eq!(output, format!("{}\n", format_diff_to_string(&expected, 0)));
+        }
+    }
+
+    #[test]
+    fn semantics_of_the_top_level_array_are_rejected() {
+        let options = DiffOptions {
+            array_semantics: vec![(PathPattern::new("").unwrap(), ArraySemantics::Unordered)],
+            ..DiffOptions::default()
+        };
+        let mut out = Vec::new();
+        let result = compare_json_streaming("[1]".as_bytes(), "[1]".as_bytes(), &options, &mut out);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn same_result_as_loading_the_documents() {
+        let cases = [
+            (r#"[{"id": "a", "v": 1}, {"id": "b", "v": 2}]"#, r#"[{"id": "a", "v": 1}, {"id": "b", "v": 3}]"#),
+            (r#"{"a": 1, "b": [1, 2], "c": "x"}"#, r#"{"a": 1, "b": [1, 2], "c": "y"}"#),
+            (r#"{"a": {"b": null}}"#, r#"{"a": {"b": null}}"#),
+            (r#"["x", "y"]"#, r#"["x", "y", "z"]"#),
+            ("1", "2"),
+        ];
+        for (before, after) in cases {
+            let (changed, output) = stream(before, after, &DiffOptions::default());
+            let before: Value = serde_json::from_str(before).unwrap();
+            let after: Value = serde_json::from_str(after).unwrap();
+            let expected = compare_json_with_options(&before, &after, &DiffOptions::default());
+            assert_eq!(changed, has_differences(&before, &after, &DiffOptions::default()));
+            assert_eq!(output, format!("{}\n", format_diff_to_string(&expected, 0)));
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Unified diff of two pretty-printed JSON structures
+//!
+//! Both documents are pretty-printed with aligned structure, as in the
+//! side-by-side view, and the differing lines are written as a standard
+//! unified diff. Each hunk header names the JSON Pointer of the first value
+//! changed in the hunk, where `diff -p` would name the enclosing function.
+
+use serde_json::Value;
+
+use crate::align::{align_documents, RowKind};
+use crate::options::DiffOptions;
+
+/// Render the differences between two JSON structures as a unified diff
+///
+/// The diff applies to the aligned pretty-printed form of the documents, in
+/// which object keys are sorted and matched array elements are placed in the
+/// same position on both sides, rather than to their original text.
+///
+/// # Arguments
+/// * `before` - The original JSON structure
+/// * `after` - The modified JSON structure
+/// * `options` - Options of the element matching used to align arrays
+/// * `before_label` - Name of the original document in the `---` header
+/// * `after_label` - Name of the modified document in the `+++` header
+/// * `context` - Number of unchanged lines shown around each change
+///
+/// # Returns
+/// The unified diff, or an empty string if the pretty-printed documents are
+/// identical
+pub fn format_unified_diff(before: &Value, after: &Value, options: &DiffOptions, before_label: &str,
+                           after_label: &str, context: usize) -> String {
+    let lines = diff_lines(before, after, options);
+    let hunks = format_hunks(&lines, context);
+    if hunks.is_empty() {
+        return String::new();
+    }
+    format!("--- {}\n+++ {}\n{}", before_label, after_label, hunks)
+}
+
+/// Group diff lines into `@@` hunks with `context` unchanged lines around each
+/// change; the header of each hunk names the path of its first changed line
+pub(crate) fn format_hunks(lines: &[DiffLine], context: usize) -> String {
+    let changes: Vec<usize> = lines.iter()
+        .enumerate()
+        .filter(|(_, line)| line.tag != ' ')
+        .map(|(k, _)| k)
+        .collect();
+
+    // Number of lines of each side preceding each diff line
+    let mut old_lines = Vec::with_capacity(lines.len() + 1);
+    let mut new_lines = Vec::with_capacity(lines.len() + 1);
+    let (mut old_count, mut new_count) = (0, 0);
+    for line in lines {
+        old_lines.push(old_count);
+        new_lines.push(new_count);
+        if line.tag != '+' {
+            old_count += 1;
+        }
+        if line.tag != '-' {
+            new_count += 1;
+        }
+    }
+    old_lines.push(old_count);
+    new_lines.push(new_count);
+
+    let mut result = String::new();
+
+    let mut n = 0;
+    while n < changes.len() {
+        // Merge changes whose context would overlap or touch
+        let first = changes[n];
+        let mut last = first;
+        while n + 1 < changes.len() && changes[n + 1] <= last + 2 * context + 1 {
+            n += 1;
+            last = changes[n];
+        }
+        n += 1;
+
+        let start = first.saturating_sub(context);
+        let end = (last + context + 1).min(lines.len());
+        let path = &lines[first].path;
+
+        result.push_str(&format!(
+            "@@ -{} +{} @@{}\n",
+            hunk_range(old_lines[start], old_lines[end] - old_lines[start]),
+            hunk_range(new_lines[start], new_lines[end] - new_lines[start]),
+            if path.is_empty() { String::new() } else { format!(" {}", path) },
+        ));
+        for line in &lines[start..end] {
+            result.push(line.tag);
+            result.push_str(&line.text);
+            result.push('\n');
+        }
+    }
+
+    result
+}
+
+/// A line of a unified diff: `' '` for unchanged, `'-'` for removed and `'+'`
+/// for added lines
+pub(crate) struct DiffLine {
+    pub tag: char,
+    pub text: String,
+    /// JSON Pointer of the value the line belongs to
+    pub path: String,
+}
+
+// Lines of the diff in order, with each run of changed lines written as its
+// removed lines followed by its added lines
+fn diff_lines(before: &Value, after: &Value, options: &DiffOptions) -> Vec<DiffLine> {
+    let mut lines = Vec::new();
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+
+    for row in align_documents(before, after, options) {
+        if row.kind == RowKind::Same {
+            lines.append(&mut removed);
+            lines.append(&mut added);
+            if let Some(text) = row.left {
+                lines.push(DiffLine { tag: ' ', text, path: row.path });
+            }
+            continue;
+        }
+        if let Some(text) = row.left {
+            removed.push(DiffLine { tag: '-', text, path: row.path.clone() });
+        }
+        if let Some(text) = row.right {
+            added.push(DiffLine { tag: '+', text, path: row.path });
+        }
+    }
+    lines.append(&mut removed);
+    lines.append(&mut added);
+
+    lines
+}
+
+// Hunk range as `start,count`, where an empty range starts at the line before
+// it and a count of one is omitted
+fn hunk_range(preceding: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", preceding),
+        1 => format!("{}", preceding + 1),
+        _ => format!("{},{}", preceding + 1, count),
+    }
+}